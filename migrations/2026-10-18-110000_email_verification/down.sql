@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE email_verification_tokens;
+ALTER TABLE users DROP COLUMN verified_at;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+-- Failed login attempts, keyed either by account ('account:<email>') or by client address ('ip:<address>')
+CREATE TABLE login_throttles (
+    key TEXT PRIMARY KEY,
+    failures INTEGER NOT NULL DEFAULT 0,
+    last_failure_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMP WITH TIME ZONE
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE moderation_actions;
+ALTER TABLE users DROP COLUMN role;
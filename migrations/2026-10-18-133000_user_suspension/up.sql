@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE users ADD COLUMN suspended_at TIMESTAMP WITH TIME ZONE;
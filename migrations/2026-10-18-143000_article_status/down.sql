@@ -0,0 +1,202 @@
+-- This file should undo anything in `up.sql`
+DROP FUNCTION get_articles;
+DROP FUNCTION select_articles;
+DROP FUNCTION user_feed;
+
+CREATE OR REPLACE FUNCTION select_articles(
+	maybe_user_id INTEGER = NULL,
+	maybe_favorited TEXT = NULL,
+	maybe_author TEXT = NULL
+) RETURNS TABLE (
+	article_slug TEXT,
+	article_title TEXT,
+	article_description TEXT,
+	article_body TEXT,
+	article_creation TIMESTAMP WITH TIME ZONE,
+	article_update TIMESTAMP WITH TIME ZONE,
+	author_username TEXT,
+	author_bio TEXT,
+	author_image TEXT,
+	tags TEXT[],
+	is_favorite BOOL,
+	is_followed BOOL ,
+    favorites_count INTEGER,
+	total_articles BIGINT
+)
+AS $$
+DECLARE 
+	follow_q TEXT = '';
+	favorite_q TEXT = '';
+	fav_result TEXT = 'false';
+	fol_result TEXT = 'false';
+	where_clause TEXT = 'WHERE 1 = 1';
+BEGIN
+
+if maybe_favorited is not null then
+	favorite_q := 'left join favorites on favorites.article_id = articles.id';
+	where_clause := where_clause || ' and favorites.user_id = (SELECT id FROM users WHERE username = ''' || maybe_favorited ||  ''' LIMIT 1) ';
+end if;
+
+if maybe_user_id is not null then
+	follow_q := 'and followings.follower_id = ' || maybe_user_id;
+	favorite_q := 'left join favorites on favorites.article_id = articles.id and favorites.user_id = ' || maybe_user_id;
+	fav_result := 'count(favorites.user_id) > 0';
+	fol_result := 'count(followings) > 0';
+end if;
+
+if maybe_author is not null then
+	where_clause := where_clause || ' and users.username = ''' || maybe_author || '''';
+end if;
+
+RETURN QUERY EXECUTE
+' select articles.slug,
+		articles.title,
+		articles.description,
+		articles.body,
+		articles.created_at,
+		articles.updated_at,
+		users.username, 
+		users.bio,
+		users.image,
+		array_agg(tags.tag) FILTER (WHERE tags.tag is not null) as tags,
+		' || fav_result || ' as is_favorite, 
+		' || fol_result || ' as is_followed,
+        articles.favorites_count,
+		count(*) over ()
+	from articles
+	inner join users on users.id = articles.author
+	left join article_tag_associations as atas on atas.article_id = articles.id
+	left join tags on atas.tag_id = tags.id
+	' || favorite_q || '
+	left join followings on followings.followed_id = articles.author ' || follow_q || '
+	' || where_clause ||'
+	group by articles.id, users.id;';
+
+END; 
+$$ LANGUAGE 'plpgsql';
+
+
+CREATE OR REPLACE FUNCTION get_articles (
+	a_limit INTEGER,
+	a_offset INTEGER, 
+	maybe_user_id INTEGER = NULL,
+	maybe_tag TEXT = NULL,
+	maybe_favorited TEXT = NULL,
+	maybe_author TEXT = NULL) 
+RETURNS TABLE (
+	article_slug TEXT,
+	article_title TEXT,
+	article_description TEXT,
+	article_body TEXT,
+	article_creation TIMESTAMP WITH TIME ZONE,
+	article_update TIMESTAMP WITH TIME ZONE,
+	author_username TEXT,
+	author_bio TEXT,
+	author_image TEXT,
+	tags TEXT[],
+	is_favorite BOOL,
+	is_followed BOOL ,
+    favorites_count INTEGER,
+	total_articles BIGINT
+) 
+AS $$
+DECLARE 
+	match TEXT[] = Array[] :: TEXT[];
+BEGIN
+
+if maybe_tag is not null then
+	match := Array[maybe_tag];
+end if;
+
+RETURN QUERY 
+SELECT * 
+FROM select_articles(maybe_user_id, maybe_favorited , maybe_author) as results
+WHERE results.tags IS NULL OR results.tags @> match
+ORDER BY results.article_creation DESC
+LIMIT a_limit 
+OFFSET a_offset;
+
+END; 
+$$ LANGUAGE 'plpgsql';
+
+CREATE OR REPLACE FUNCTION user_feed(feed_user_id INTEGER)
+RETURNS TABLE (
+	article_slug TEXT,
+	article_title TEXT,
+	article_description TEXT,
+	article_body TEXT,
+	article_creation TIMESTAMP WITH TIME ZONE,
+	article_update TIMESTAMP WITH TIME ZONE,
+	author_username TEXT,
+	author_bio TEXT,
+	author_image TEXT,
+	tags TEXT[],
+	is_favorite BOOL,
+	is_followed BOOL ,
+    favorites_count INTEGER,
+	total_articles BIGINT
+)
+AS $$
+BEGIN
+RETURN QUERY 
+select articles.slug as article_slug,
+		articles.title as article_title,
+		articles.description as article_description,
+		articles.body as article_body,
+		articles.created_at as article_creation,
+		articles.updated_at as article_update,
+		users.username as author_username, 
+		users.bio as author_bio,
+		users.image as author_image,
+		array_agg(tags.tag) FILTER (WHERE tags.tag is not null) as tags,
+		count(favorites.user_id) > 0 as is_favorite, 
+		count(followings) > 0 as is_followed,
+        articles.favorites_count as favorites_count,
+		count(*) OVER()
+	from articles
+	inner join users on users.id = articles.author
+	left join article_tag_associations as atas on atas.article_id = articles.id
+	left join tags on atas.tag_id = tags.id
+	left join favorites on favorites.article_id = articles.id and favorites.user_id = feed_user_id
+	inner join followings on followings.followed_id = articles.author and followings.follower_id = feed_user_id
+	group by articles.id, users.id
+    order by articles.created_at DESC;
+END; 
+$$ LANGUAGE 'plpgsql';
+
+CREATE OR REPLACE FUNCTION get_comments(a_article_slug TEXT, m_user_id INTEGER = NULL)
+RETURNS TABLE (
+    comment_id INTEGER,
+    comment_body TEXT,
+    comment_creation TIMESTAMP WITH TIME ZONE,
+    comment_update TIMESTAMP WITH TIME ZONE,
+    author_username TEXT,
+    author_bio TEXT,
+    author_image TEXT,
+    is_followed BOOL,
+    total_comments BIGINT
+) AS $$ 
+BEGIN
+RETURN QUERY 
+    SELECT 
+        comments.id,
+        comments.body,
+        comments.created_at,
+        comments.updated_at,
+        users.username,
+        users.bio,
+        users.image,
+        count(followings) > 0,
+        count(*) over()
+    FROM comments
+    INNER JOIN articles ON articles.id = comments.article_id
+    INNER JOIN users ON comments.user_id = users.id
+    LEFT JOIN followings ON followings.followed_id = users.id AND followings.follower_id = m_user_id
+    WHERE articles.slug = a_article_slug
+    GROUP BY comments.id, users.username, users.bio, users.image
+    ORDER BY comments.created_at DESC;
+END;
+$$ LANGUAGE 'plpgsql';
+
+DROP INDEX articles_status_idx;
+ALTER TABLE articles DROP COLUMN status;
@@ -0,0 +1,22 @@
+-- Your SQL goes here
+CREATE TABLE article_revisions (
+    id SERIAL PRIMARY KEY,
+    article_id INTEGER NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    body TEXT NOT NULL,
+    tag_list TEXT[] NOT NULL DEFAULT '{}',
+    editor_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT article_revisions_number UNIQUE (article_id, revision)
+);
+
+-- The current content of existing articles becomes their first revision
+INSERT INTO article_revisions (article_id, revision, title, description, body, tag_list, editor_id, created_at)
+SELECT id, 1, title, description, body, coalesce((
+	SELECT array_agg(tags.tag ORDER BY tags.tag)
+	FROM article_tag_associations AS atas
+	INNER JOIN tags ON tags.id = atas.tag_id
+	WHERE atas.article_id = articles.id
+), '{}'), author, updated_at FROM articles;
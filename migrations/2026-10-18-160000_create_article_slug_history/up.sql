@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE article_slug_history (
+    slug TEXT PRIMARY KEY,
+    article_id INTEGER NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX article_slug_history_article ON article_slug_history (article_id);
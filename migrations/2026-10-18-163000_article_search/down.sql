@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP FUNCTION search_articles;
+DROP TRIGGER article_search_on_tags ON article_tag_associations;
+DROP TRIGGER article_search_on_article ON articles;
+DROP FUNCTION refresh_article_search_on_tags;
+DROP FUNCTION refresh_article_search_on_article;
+DROP FUNCTION refresh_article_search;
+DROP TABLE article_search;
@@ -0,0 +1,124 @@
+-- Your SQL goes here
+CREATE TABLE article_search (
+    article_id INTEGER PRIMARY KEY REFERENCES articles(id) ON DELETE CASCADE,
+    document TSVECTOR NOT NULL
+);
+
+CREATE INDEX article_search_document ON article_search USING GIN (document);
+
+-- The title weighs the most, then the tags, the description and the body
+CREATE FUNCTION refresh_article_search(refreshed_id INTEGER) RETURNS VOID AS $$
+INSERT INTO article_search (article_id, document)
+SELECT articles.id,
+	setweight(to_tsvector('english', articles.title), 'A') ||
+	setweight(to_tsvector('english', coalesce(string_agg(tags.tag, ' '), '')), 'B') ||
+	setweight(to_tsvector('english', articles.description), 'C') ||
+	setweight(to_tsvector('english', articles.body), 'D')
+FROM articles
+LEFT JOIN article_tag_associations AS atas ON atas.article_id = articles.id
+LEFT JOIN tags ON tags.id = atas.tag_id
+WHERE articles.id = refreshed_id
+GROUP BY articles.id
+ON CONFLICT (article_id) DO UPDATE SET document = EXCLUDED.document;
+$$ LANGUAGE SQL;
+
+CREATE FUNCTION refresh_article_search_on_article() RETURNS TRIGGER AS $$
+BEGIN
+	PERFORM refresh_article_search(NEW.id);
+	RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER article_search_on_article
+AFTER INSERT OR UPDATE OF title, description, body ON articles
+FOR EACH ROW EXECUTE PROCEDURE refresh_article_search_on_article();
+
+CREATE FUNCTION refresh_article_search_on_tags() RETURNS TRIGGER AS $$
+BEGIN
+	IF TG_OP = 'DELETE' THEN
+		PERFORM refresh_article_search(OLD.article_id);
+	ELSE
+		PERFORM refresh_article_search(NEW.article_id);
+	END IF;
+	RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER article_search_on_tags
+AFTER INSERT OR DELETE ON article_tag_associations
+FOR EACH ROW EXECUTE PROCEDURE refresh_article_search_on_tags();
+
+SELECT refresh_article_search(id) FROM articles;
+
+-- Visible articles matching a web search style query, best matches first, with
+-- the matched terms wrapped in <mark> tags.
+CREATE FUNCTION search_articles(
+	search TEXT,
+	a_limit INTEGER,
+	a_offset INTEGER,
+	maybe_user_id INTEGER = NULL
+) RETURNS TABLE (
+	article_slug TEXT,
+	article_title TEXT,
+	article_description TEXT,
+	article_body TEXT,
+	article_creation TIMESTAMP WITH TIME ZONE,
+	article_update TIMESTAMP WITH TIME ZONE,
+	article_status TEXT,
+	article_publication TIMESTAMP WITH TIME ZONE,
+	author_username TEXT,
+	author_bio TEXT,
+	author_image TEXT,
+	tags TEXT[],
+	is_favorite BOOL,
+	is_followed BOOL,
+	favorites_count INTEGER,
+	total_articles BIGINT,
+	title_highlight TEXT,
+	description_highlight TEXT,
+	body_highlight TEXT
+)
+AS $$
+#variable_conflict use_column
+DECLARE
+	search_query tsquery = websearch_to_tsquery('english', search);
+	highlight TEXT = 'StartSel=<mark>, StopSel=</mark>, HighlightAll=true';
+	fragments TEXT = 'StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MaxWords=30, MinWords=10';
+BEGIN
+-- Highlights are only computed for the returned page
+RETURN QUERY
+SELECT matches.article_slug,
+		matches.article_title,
+		matches.article_description,
+		matches.article_body,
+		matches.article_creation,
+		matches.article_update,
+		matches.article_status,
+		matches.article_publication,
+		matches.author_username,
+		matches.author_bio,
+		matches.author_image,
+		matches.tags,
+		matches.is_favorite,
+		matches.is_followed,
+		matches.favorites_count,
+		matches.matching_articles,
+		ts_headline('english', matches.article_title, search_query, highlight),
+		ts_headline('english', matches.article_description, search_query, highlight),
+		ts_headline('english', matches.article_body, search_query, fragments)
+FROM (
+	SELECT results.*,
+		count(*) OVER () AS matching_articles,
+		ts_rank(article_search.document, search_query) AS search_rank
+	FROM select_articles(maybe_user_id) AS results
+	INNER JOIN articles ON articles.slug = results.article_slug
+	INNER JOIN article_search ON article_search.article_id = articles.id
+	WHERE article_search.document @@ search_query
+	ORDER BY search_rank DESC, results.article_publication DESC NULLS LAST
+	LIMIT a_limit
+	OFFSET a_offset
+) AS matches
+ORDER BY matches.search_rank DESC, matches.article_publication DESC NULLS LAST;
+
+END;
+$$ LANGUAGE 'plpgsql';
@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX tags_tag_trgm;
+DROP INDEX users_username_trgm;
+
+-- Only drop the extension if up.sql created it
+DO $$
+BEGIN
+	IF obj_description((SELECT oid FROM pg_extension WHERE extname = 'pg_trgm'), 'pg_extension')
+		= 'Created by the trigram_lookup migration' THEN
+		DROP EXTENSION pg_trgm;
+	END IF;
+END $$;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+-- The extension may already be installed, in which case it isn't ours to drop
+DO $$
+BEGIN
+	IF NOT EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'pg_trgm') THEN
+		CREATE EXTENSION pg_trgm;
+		COMMENT ON EXTENSION pg_trgm IS 'Created by the trigram_lookup migration';
+	END IF;
+END $$;
+
+-- Used by both the prefix and the similarity lookups of autocompletion
+CREATE INDEX users_username_trgm ON users USING GIN (username gin_trgm_ops);
+CREATE INDEX tags_tag_trgm ON tags USING GIN (tag gin_trgm_ops);
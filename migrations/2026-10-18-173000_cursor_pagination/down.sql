@@ -0,0 +1,48 @@
+-- This file should undo anything in `up.sql`
+DROP FUNCTION get_articles;
+
+CREATE FUNCTION get_articles (
+	a_limit INTEGER,
+	a_offset INTEGER, 
+	maybe_user_id INTEGER = NULL,
+	maybe_tag TEXT = NULL,
+	maybe_favorited TEXT = NULL,
+	maybe_author TEXT = NULL,
+	maybe_status TEXT = NULL) 
+RETURNS TABLE (
+	article_slug TEXT,
+	article_title TEXT,
+	article_description TEXT,
+	article_body TEXT,
+	article_creation TIMESTAMP WITH TIME ZONE,
+	article_update TIMESTAMP WITH TIME ZONE,
+	article_status TEXT,
+	article_publication TIMESTAMP WITH TIME ZONE,
+	author_username TEXT,
+	author_bio TEXT,
+	author_image TEXT,
+	tags TEXT[],
+	is_favorite BOOL,
+	is_followed BOOL ,
+    favorites_count INTEGER,
+	total_articles BIGINT
+) 
+AS $$
+DECLARE 
+	match TEXT[] = Array[] :: TEXT[];
+BEGIN
+
+if maybe_tag is not null then
+	match := Array[maybe_tag];
+end if;
+
+RETURN QUERY 
+SELECT * 
+FROM select_articles(maybe_user_id, maybe_favorited , maybe_author, NULL, maybe_status) as results
+WHERE results.tags IS NULL OR results.tags @> match
+ORDER BY results.article_publication DESC NULLS LAST, results.article_creation DESC
+LIMIT a_limit 
+OFFSET a_offset;
+
+END; 
+$$ LANGUAGE 'plpgsql';
@@ -0,0 +1,65 @@
+-- Your SQL goes here
+DROP FUNCTION get_articles;
+
+-- Articles are listed by publication date, or creation date for drafts, then by
+-- slug so that a cursor designates an exact position. Backwards cursors select
+-- the page before them, which is still returned in listing order.
+CREATE FUNCTION get_articles (
+	a_limit INTEGER,
+	a_offset INTEGER, 
+	maybe_user_id INTEGER = NULL,
+	maybe_tag TEXT = NULL,
+	maybe_favorited TEXT = NULL,
+	maybe_author TEXT = NULL,
+	maybe_status TEXT = NULL,
+	cursor_time TIMESTAMP WITH TIME ZONE = NULL,
+	cursor_slug TEXT = NULL,
+	backwards BOOL = false) 
+RETURNS TABLE (
+	article_slug TEXT,
+	article_title TEXT,
+	article_description TEXT,
+	article_body TEXT,
+	article_creation TIMESTAMP WITH TIME ZONE,
+	article_update TIMESTAMP WITH TIME ZONE,
+	article_status TEXT,
+	article_publication TIMESTAMP WITH TIME ZONE,
+	author_username TEXT,
+	author_bio TEXT,
+	author_image TEXT,
+	tags TEXT[],
+	is_favorite BOOL,
+	is_followed BOOL ,
+    favorites_count INTEGER,
+	total_articles BIGINT
+) 
+AS $$
+DECLARE 
+	match TEXT[] = Array[] :: TEXT[];
+BEGIN
+
+if maybe_tag is not null then
+	match := Array[maybe_tag];
+end if;
+
+RETURN QUERY 
+SELECT page.*
+FROM (
+	SELECT * 
+	FROM select_articles(maybe_user_id, maybe_favorited , maybe_author, NULL, maybe_status) as results
+	WHERE (results.tags IS NULL OR results.tags @> match)
+	AND (cursor_time IS NULL
+		OR (backwards AND (coalesce(results.article_publication, results.article_creation), results.article_slug) > (cursor_time, cursor_slug))
+		OR (NOT backwards AND (coalesce(results.article_publication, results.article_creation), results.article_slug) < (cursor_time, cursor_slug)))
+	ORDER BY
+		CASE WHEN backwards THEN coalesce(results.article_publication, results.article_creation) END ASC,
+		CASE WHEN backwards THEN results.article_slug END ASC,
+		coalesce(results.article_publication, results.article_creation) DESC,
+		results.article_slug DESC
+	LIMIT a_limit 
+	OFFSET a_offset
+) AS page
+ORDER BY coalesce(page.article_publication, page.article_creation) DESC, page.article_slug DESC;
+
+END; 
+$$ LANGUAGE 'plpgsql';
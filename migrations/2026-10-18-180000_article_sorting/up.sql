@@ -0,0 +1,117 @@
+-- Your SQL goes here
+DROP FUNCTION get_articles;
+
+-- Articles are sorted by a rank, which is 0 unless sorting by a count, then by a
+-- time and finally by slug so that a cursor designates an exact position. Both
+-- are returned to build cursors. Backwards cursors select the page before them,
+-- which is still returned in listing order.
+--
+-- Sorts: newest and oldest by publication date, or creation date for drafts,
+-- favorited and commented by count then publication date, updated by last update.
+CREATE FUNCTION get_articles (
+	a_limit INTEGER,
+	a_offset INTEGER, 
+	maybe_user_id INTEGER = NULL,
+	maybe_tag TEXT = NULL,
+	maybe_favorited TEXT = NULL,
+	maybe_author TEXT = NULL,
+	maybe_status TEXT = NULL,
+	a_sort TEXT = 'newest',
+	cursor_rank BIGINT = 0,
+	cursor_time TIMESTAMP WITH TIME ZONE = NULL,
+	cursor_slug TEXT = NULL,
+	backwards BOOL = false) 
+RETURNS TABLE (
+	article_slug TEXT,
+	article_title TEXT,
+	article_description TEXT,
+	article_body TEXT,
+	article_creation TIMESTAMP WITH TIME ZONE,
+	article_update TIMESTAMP WITH TIME ZONE,
+	article_status TEXT,
+	article_publication TIMESTAMP WITH TIME ZONE,
+	author_username TEXT,
+	author_bio TEXT,
+	author_image TEXT,
+	tags TEXT[],
+	is_favorite BOOL,
+	is_followed BOOL ,
+    favorites_count INTEGER,
+	total_articles BIGINT,
+	sort_rank BIGINT,
+	sort_time TIMESTAMP WITH TIME ZONE
+) 
+AS $$
+DECLARE 
+	match TEXT[] = Array[] :: TEXT[];
+	ascending BOOL = a_sort = 'oldest';
+	-- Direction in which the page is looked for from the cursor
+	scan_ascending BOOL = ascending <> backwards;
+BEGIN
+
+if maybe_tag is not null then
+	match := Array[maybe_tag];
+end if;
+
+RETURN QUERY 
+SELECT page.article_slug,
+		page.article_title,
+		page.article_description,
+		page.article_body,
+		page.article_creation,
+		page.article_update,
+		page.article_status,
+		page.article_publication,
+		page.author_username,
+		page.author_bio,
+		page.author_image,
+		page.tags,
+		page.is_favorite,
+		page.is_followed,
+		page.favorites_count,
+		page.total_articles,
+		page.sort_rank,
+		page.sort_time
+FROM (
+	SELECT sorted.*
+	FROM (
+		SELECT results.*,
+			CASE a_sort
+				WHEN 'favorited' THEN results.favorites_count :: BIGINT
+				WHEN 'commented' THEN (
+					SELECT count(*)
+					FROM comments
+					INNER JOIN articles ON articles.id = comments.article_id
+					WHERE articles.slug = results.article_slug)
+				ELSE 0
+			END AS sort_rank,
+			CASE a_sort
+				WHEN 'updated' THEN results.article_update
+				ELSE coalesce(results.article_publication, results.article_creation)
+			END AS sort_time
+		FROM select_articles(maybe_user_id, maybe_favorited , maybe_author, NULL, maybe_status) as results
+		WHERE results.tags IS NULL OR results.tags @> match
+	) AS sorted
+	WHERE cursor_time IS NULL
+		OR (scan_ascending AND (sorted.sort_rank, sorted.sort_time, sorted.article_slug) > (cursor_rank, cursor_time, cursor_slug))
+		OR (NOT scan_ascending AND (sorted.sort_rank, sorted.sort_time, sorted.article_slug) < (cursor_rank, cursor_time, cursor_slug))
+	ORDER BY
+		CASE WHEN scan_ascending THEN sorted.sort_rank END ASC,
+		CASE WHEN scan_ascending THEN sorted.sort_time END ASC,
+		CASE WHEN scan_ascending THEN sorted.article_slug END ASC,
+		sorted.sort_rank DESC,
+		sorted.sort_time DESC,
+		sorted.article_slug DESC
+	LIMIT a_limit 
+	OFFSET a_offset
+) AS page
+ORDER BY
+	CASE WHEN ascending THEN page.sort_rank END ASC,
+	CASE WHEN ascending THEN page.sort_time END ASC,
+	CASE WHEN ascending THEN page.article_slug END ASC,
+	page.sort_rank DESC,
+	page.sort_time DESC,
+	page.article_slug DESC;
+
+END; 
+$$ LANGUAGE 'plpgsql';
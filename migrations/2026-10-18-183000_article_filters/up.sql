@@ -0,0 +1,234 @@
+-- Your SQL goes here
+DROP FUNCTION get_articles;
+DROP FUNCTION select_articles;
+
+-- Published articles are visible to everyone once their publication date is
+-- reached, unlisted ones only when looked up by slug, and drafts only to their author.
+-- Favorites of maybe_favorited are joined apart from those of the user, so that
+-- filtering on them doesn't hide whether the user favorited the article.
+CREATE FUNCTION select_articles(
+	maybe_user_id INTEGER = NULL,
+	maybe_favorited TEXT = NULL,
+	maybe_author TEXT = NULL,
+	maybe_slug TEXT = NULL,
+	maybe_status TEXT = NULL
+) RETURNS TABLE (
+	article_slug TEXT,
+	article_title TEXT,
+	article_description TEXT,
+	article_body TEXT,
+	article_creation TIMESTAMP WITH TIME ZONE,
+	article_update TIMESTAMP WITH TIME ZONE,
+	article_status TEXT,
+	article_publication TIMESTAMP WITH TIME ZONE,
+	author_username TEXT,
+	author_bio TEXT,
+	author_image TEXT,
+	tags TEXT[],
+	is_favorite BOOL,
+	is_followed BOOL ,
+    favorites_count INTEGER,
+	total_articles BIGINT
+)
+AS $$
+DECLARE 
+	follow_q TEXT = '';
+	favorite_q TEXT = '';
+	favorited_q TEXT = '';
+	fav_result TEXT = 'false';
+	fol_result TEXT = 'false';
+	where_clause TEXT = 'WHERE 1 = 1';
+	visible TEXT = '(articles.status = ''published'' and articles.published_at <= now())';
+BEGIN
+
+if maybe_favorited is not null then
+	favorited_q := 'inner join favorites as favorited_by on favorited_by.article_id = articles.id and favorited_by.user_id = (SELECT id FROM users WHERE username = ' || quote_literal(maybe_favorited) ||  ' LIMIT 1)';
+end if;
+
+if maybe_user_id is not null then
+	follow_q := 'and followings.follower_id = ' || maybe_user_id;
+	favorite_q := 'left join favorites as viewer_favorites on viewer_favorites.article_id = articles.id and viewer_favorites.user_id = ' || maybe_user_id;
+	fav_result := 'count(viewer_favorites.user_id) > 0';
+	fol_result := 'count(followings) > 0';
+end if;
+
+-- Authors only see their own unpublished articles when looking them up or
+-- listing them by status, as /user/drafts does, not in the public listings
+if maybe_user_id is not null and (maybe_slug is not null or maybe_status is not null) then
+	visible := visible || ' or articles.author = ' || maybe_user_id;
+end if;
+
+if maybe_author is not null then
+	where_clause := where_clause || ' and users.username = ' || quote_literal(maybe_author);
+end if;
+
+if maybe_slug is not null then
+	where_clause := where_clause || ' and articles.slug = ' || quote_literal(maybe_slug);
+	visible := visible || ' or articles.status = ''unlisted''';
+end if;
+
+if maybe_status is not null then
+	where_clause := where_clause || ' and articles.status = ' || quote_literal(maybe_status);
+end if;
+
+where_clause := where_clause || ' and (' || visible || ')';
+
+RETURN QUERY EXECUTE
+' select articles.slug,
+		articles.title,
+		articles.description,
+		articles.body,
+		articles.created_at,
+		articles.updated_at,
+		articles.status,
+		articles.published_at,
+		users.username, 
+		users.bio,
+		users.image,
+		array_agg(tags.tag) FILTER (WHERE tags.tag is not null) as tags,
+		' || fav_result || ' as is_favorite, 
+		' || fol_result || ' as is_followed,
+        articles.favorites_count,
+		count(*) over ()
+	from articles
+	inner join users on users.id = articles.author
+	left join article_tag_associations as atas on atas.article_id = articles.id
+	left join tags on atas.tag_id = tags.id
+	' || favorited_q || '
+	' || favorite_q || '
+	left join followings on followings.followed_id = articles.author ' || follow_q || '
+	' || where_clause ||'
+	group by articles.id, users.id;';
+
+END; 
+$$ LANGUAGE 'plpgsql';
+
+-- Articles are sorted by a rank, which is 0 unless sorting by a count, then by a
+-- time and finally by slug so that a cursor designates an exact position. Both
+-- are returned to build cursors. Backwards cursors select the page before them,
+-- which is still returned in listing order.
+--
+-- Sorts: newest and oldest by publication date, or creation date for drafts,
+-- favorited and commented by count then publication date, updated by last update.
+--
+-- Filters: articles having any of match_tags, or all of them with match_all, none
+-- of excluded_tags, written by one of author_names and published from
+-- published_since included to published_until excluded. Empty arrays and NULL
+-- bounds don't filter anything. total_articles counts the filtered articles.
+CREATE FUNCTION get_articles (
+	a_limit INTEGER,
+	a_offset INTEGER, 
+	maybe_user_id INTEGER = NULL,
+	match_tags TEXT[] = '{}',
+	match_all BOOL = false,
+	excluded_tags TEXT[] = '{}',
+	author_names TEXT[] = '{}',
+	maybe_favorited TEXT = NULL,
+	maybe_status TEXT = NULL,
+	published_since TIMESTAMP WITH TIME ZONE = NULL,
+	published_until TIMESTAMP WITH TIME ZONE = NULL,
+	a_sort TEXT = 'newest',
+	cursor_rank BIGINT = 0,
+	cursor_time TIMESTAMP WITH TIME ZONE = NULL,
+	cursor_slug TEXT = NULL,
+	backwards BOOL = false) 
+RETURNS TABLE (
+	article_slug TEXT,
+	article_title TEXT,
+	article_description TEXT,
+	article_body TEXT,
+	article_creation TIMESTAMP WITH TIME ZONE,
+	article_update TIMESTAMP WITH TIME ZONE,
+	article_status TEXT,
+	article_publication TIMESTAMP WITH TIME ZONE,
+	author_username TEXT,
+	author_bio TEXT,
+	author_image TEXT,
+	tags TEXT[],
+	is_favorite BOOL,
+	is_followed BOOL ,
+    favorites_count INTEGER,
+	total_articles BIGINT,
+	sort_rank BIGINT,
+	sort_time TIMESTAMP WITH TIME ZONE
+) 
+AS $$
+DECLARE 
+	ascending BOOL = a_sort = 'oldest';
+	-- Direction in which the page is looked for from the cursor
+	scan_ascending BOOL = ascending <> backwards;
+BEGIN
+
+RETURN QUERY 
+SELECT page.article_slug,
+		page.article_title,
+		page.article_description,
+		page.article_body,
+		page.article_creation,
+		page.article_update,
+		page.article_status,
+		page.article_publication,
+		page.author_username,
+		page.author_bio,
+		page.author_image,
+		page.tags,
+		page.is_favorite,
+		page.is_followed,
+		page.favorites_count,
+		page.filtered_total,
+		page.sort_rank,
+		page.sort_time
+FROM (
+	SELECT sorted.*
+	FROM (
+		SELECT results.*,
+			CASE a_sort
+				WHEN 'favorited' THEN results.favorites_count :: BIGINT
+				WHEN 'commented' THEN (
+					SELECT count(*)
+					FROM comments
+					INNER JOIN articles ON articles.id = comments.article_id
+					WHERE articles.slug = results.article_slug)
+				ELSE 0
+			END AS sort_rank,
+			CASE a_sort
+				WHEN 'updated' THEN results.article_update
+				ELSE coalesce(results.article_publication, results.article_creation)
+			END AS sort_time,
+			count(*) OVER () AS filtered_total
+		FROM select_articles(maybe_user_id, maybe_favorited , NULL, NULL, maybe_status) as results
+		WHERE (cardinality(match_tags) = 0
+				OR (match_all AND results.tags @> match_tags)
+				OR (NOT match_all AND results.tags && match_tags))
+			AND (cardinality(excluded_tags) = 0
+				OR results.tags IS NULL
+				OR NOT results.tags && excluded_tags)
+			AND (cardinality(author_names) = 0 OR results.author_username = ANY(author_names))
+			AND (published_since IS NULL
+				OR coalesce(results.article_publication, results.article_creation) >= published_since)
+			AND (published_until IS NULL
+				OR coalesce(results.article_publication, results.article_creation) < published_until)
+	) AS sorted
+	WHERE cursor_time IS NULL
+		OR (scan_ascending AND (sorted.sort_rank, sorted.sort_time, sorted.article_slug) > (cursor_rank, cursor_time, cursor_slug))
+		OR (NOT scan_ascending AND (sorted.sort_rank, sorted.sort_time, sorted.article_slug) < (cursor_rank, cursor_time, cursor_slug))
+	ORDER BY
+		CASE WHEN scan_ascending THEN sorted.sort_rank END ASC,
+		CASE WHEN scan_ascending THEN sorted.sort_time END ASC,
+		CASE WHEN scan_ascending THEN sorted.article_slug END ASC,
+		sorted.sort_rank DESC,
+		sorted.sort_time DESC,
+		sorted.article_slug DESC
+	LIMIT a_limit 
+	OFFSET a_offset
+) AS page
+ORDER BY
+	CASE WHEN ascending THEN page.sort_rank END ASC,
+	CASE WHEN ascending THEN page.sort_time END ASC,
+	CASE WHEN ascending THEN page.article_slug END ASC,
+	page.sort_rank DESC,
+	page.sort_time DESC,
+	page.article_slug DESC;
+
+END; 
+$$ LANGUAGE 'plpgsql';
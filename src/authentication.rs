@@ -1,43 +1,102 @@
 use crate::config::Config;
-use crate::db::DbResult;
+use crate::db;
+use crate::db::{DbConnection, DbResult};
 use crate::errors::Error;
-use jsonwebtoken::{encode, DecodingKey, EncodingKey, Header, Validation};
+use crate::models::user::Role;
+use crate::tokens;
+use chrono::Utc;
+use jsonwebtoken::{encode, Header, Validation};
 use rocket;
 use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome, Request};
 use rocket::State;
 use serde::{Deserialize, Serialize};
 
+pub const AUTHORIZATION_SCHEMES: &[&str] = &["Token", "Bearer"];
+/// Cookie holding the access token of browser sessions, when enabled with `SESSION_COOKIE`.
+pub const SESSION_COOKIE: &str = "conduit_session";
+
 #[derive(Serialize, Deserialize)]
 pub struct AuthData {
     pub id: i32,
     pub username: String,
-    pub exp: i32,
+    pub exp: i64,
+    pub jti: String,
+    pub ver: i32,
+    /// Set when the request was authenticated with an API key rather than a JWT
+    #[serde(skip)]
+    pub api_key: Option<i32>,
+    /// Scopes granted by the API key, `None` meaning unrestricted
+    #[serde(skip)]
+    pub scopes: Option<Vec<String>>,
 }
 
-pub fn encode_token(id: i32, username: &String, secret: &String) -> DbResult<String> {
+pub fn encode_token(
+    id: i32,
+    username: &String,
+    token_version: i32,
+    config: &Config,
+) -> DbResult<String> {
+    let signing = &config.jwt_keys.signing;
+    let mut header = Header::new(signing.algorithm);
+    header.kid = signing.kid.clone();
     encode(
-        &Header::default(),
+        &header,
         &AuthData {
             id,
             username: username.clone(),
-            exp: i32::max_value(),
+            exp: (Utc::now() + config.access_token_lifetime).timestamp(),
+            jti: tokens::generate(),
+            ver: token_version,
+            api_key: None,
+            scopes: None,
         },
-        &EncodingKey::from_secret(secret.as_ref()),
+        &signing.key,
     )
     .map_err(|err| Error::InternalServerError("jwt".to_owned(), err.to_string()))
 }
 
 impl AuthData {
+    pub fn require_scope(&self, scope: &str) -> DbResult<()> {
+        match &self.scopes {
+            Some(scopes) if !scopes.iter().any(|s| s == scope) => Err(Error::Forbidden),
+            _ => Ok(()),
+        }
+    }
+
+    /// Some operations, like managing credentials, can't be performed with an API key.
+    pub fn require_session(&self) -> DbResult<()> {
+        match self.api_key {
+            Some(_) => Err(Error::Forbidden),
+            None => Ok(()),
+        }
+    }
+
+    /// Role of the authenticated user. Users who verified an email listed in
+    /// `ADMIN_EMAILS` are always admins, which allows bootstrapping the first one.
+    pub fn role(&self, conn: &DbConnection, config: &Config) -> DbResult<Role> {
+        let user = db::users::find_by_id(conn, self.id)?;
+        let bootstrapped = user.verified_at.is_some()
+            && config
+                .admin_emails
+                .iter()
+                .any(|email| email.eq_ignore_ascii_case(&user.email));
+        if bootstrapped {
+            Ok(Role::Admin)
+        } else {
+            Ok(Role::parse(&user.role).unwrap_or(Role::User))
+        }
+    }
+
     fn decode(token: &str, config: &Config) -> Option<Self> {
-        jsonwebtoken::decode(
-            token,
-            &DecodingKey::from_secret(&config.secret.as_ref()),
-            &Validation::default(),
-        )
-        .map_err(|e| println!["{}", e])
-        .ok()
-        .map(|data| data.claims)
+        let header = jsonwebtoken::decode_header(token).ok()?;
+        let key = config.jwt_keys.find(&header.kid)?;
+        if header.alg != key.algorithm {
+            return None;
+        }
+        jsonwebtoken::decode(token, &key.key, &Validation::new(key.algorithm))
+            .ok()
+            .map(|data| data.claims)
     }
 }
 
@@ -46,18 +105,100 @@ impl<'a, 'r> FromRequest<'a, 'r> for AuthData {
 
     fn from_request(request: &'a Request<'r>) -> Outcome<AuthData, Self::Error> {
         let config: State<Config> = request.guard()?;
-        match request.headers().get_one("authorization") {
-            Some(auth_header) => {
-                if auth_header[0..6].to_lowercase() == "token " {
-                    match AuthData::decode(&auth_header[6..], config.inner()) {
-                        Some(data) => Outcome::Success(data),
-                        None => Outcome::Failure((Status::Forbidden, ())),
-                    }
-                } else {
-                    Outcome::Failure((Status::Forbidden, ()))
-                }
-            }
+        let credentials = match request.headers().get_one("authorization") {
+            Some(auth_header) => parse_authorization(auth_header).map(|c| c.to_owned()),
+            None if config.session_cookie => request
+                .cookies()
+                .get(SESSION_COOKIE)
+                .map(|cookie| cookie.value().to_owned()),
+            None => None,
+        };
+        let credentials = match credentials {
+            Some(credentials) => credentials,
+            None => return Outcome::Failure((Status::Unauthorized, ())),
+        };
+
+        let conn: DbConnection = request.guard()?;
+        let authenticated = if credentials.starts_with(db::api_keys::KEY_PREFIX) {
+            db::api_keys::authenticate(&conn, &credentials)
+        } else {
+            AuthData::decode(&credentials, config.inner())
+                .filter(|data| db::revocations::is_active(&conn, data))
+        };
+        match authenticated {
+            Some(data) => Outcome::Success(data),
             None => Outcome::Failure((Status::Unauthorized, ())),
         }
     }
 }
+
+/// Extracts the credentials of an `Authorization` header using either the
+/// Conduit specific `Token` scheme or the standard `Bearer` one.
+fn parse_authorization(header: &str) -> Option<&str> {
+    let mut parts = header.trim().splitn(2, char::is_whitespace);
+    let scheme = parts.next()?;
+    let credentials = parts.next()?.trim();
+    let known_scheme = AUTHORIZATION_SCHEMES
+        .iter()
+        .any(|known| scheme.eq_ignore_ascii_case(known));
+    if known_scheme && !credentials.is_empty() {
+        Some(credentials)
+    } else {
+        None
+    }
+}
+
+/// Authenticated user with the admin role.
+pub struct AdminUser(pub AuthData);
+
+impl<'a, 'r> FromRequest<'a, 'r> for AdminUser {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> Outcome<AdminUser, Self::Error> {
+        require_role(request, Role::Admin).map(AdminUser)
+    }
+}
+
+/// Authenticated user with the moderator role or above.
+pub struct ModeratorUser(pub AuthData);
+
+impl<'a, 'r> FromRequest<'a, 'r> for ModeratorUser {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> Outcome<ModeratorUser, Self::Error> {
+        require_role(request, Role::Moderator).map(ModeratorUser)
+    }
+}
+
+// Privileged routes are off limits to API keys, whatever their scopes
+fn require_role<'a, 'r>(request: &'a Request<'r>, minimum: Role) -> Outcome<AuthData, ()> {
+    let auth: AuthData = request.guard()?;
+    if auth.require_session().is_err() {
+        return Outcome::Failure((Status::Forbidden, ()));
+    }
+    let config: State<Config> = request.guard()?;
+    let conn: DbConnection = request.guard()?;
+    match auth.role(&conn, &config) {
+        Ok(role) if role >= minimum => Outcome::Success(auth),
+        _ => Outcome::Failure((Status::Forbidden, ())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_authorization;
+
+    #[test]
+    fn rejects_malformed_headers() {
+        for header in ["", "Token", "Bearer", "Bearer   ", "é", "Basic abc"].iter() {
+            assert_eq!(parse_authorization(header), None, "{:?}", header);
+        }
+    }
+
+    #[test]
+    fn accepts_token_and_bearer_schemes() {
+        assert_eq!(parse_authorization("Token abc.def"), Some("abc.def"));
+        assert_eq!(parse_authorization("Bearer abc.def"), Some("abc.def"));
+        assert_eq!(parse_authorization(" bearer  abc.def "), Some("abc.def"));
+    }
+}
@@ -0,0 +1,21 @@
+use crate::config::Config;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::State;
+use std::net::IpAddr;
+
+/// Address of the client. The `X-Real-IP` header set by a reverse proxy is only
+/// honored with `TRUST_PROXY_HEADERS`, since clients could otherwise forge it.
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl<'a, 'r> FromRequest<'a, 'r> for ClientIp {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> Outcome<ClientIp, Self::Error> {
+        let config: State<Config> = request.guard()?;
+        Outcome::Success(ClientIp(if config.trust_proxy_headers {
+            request.client_ip()
+        } else {
+            request.remote().map(|address| address.ip())
+        }))
+    }
+}
@@ -0,0 +1,111 @@
+use crate::authentication::SESSION_COOKIE;
+use crate::format::{decode_http_date, encode_http_date};
+use crate::tokens;
+use chrono::NaiveDateTime;
+use rocket::http::{Method, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::{self, Responder, Response};
+use rocket_contrib::json::JsonValue;
+
+/// How long shared caches may serve anonymous responses without revalidating.
+const ANONYMOUS_MAX_AGE: u32 = 60;
+
+/// Value of the `If-Match` header, used to detect concurrent modifications.
+pub struct IfMatch(pub Option<String>);
+
+impl<'a, 'r> FromRequest<'a, 'r> for IfMatch {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> Outcome<IfMatch, Self::Error> {
+        Outcome::Success(IfMatch(
+            request.headers().get_one("If-Match").map(|v| v.to_owned()),
+        ))
+    }
+}
+
+/// Entity tag made of the `version` of a resource, which `If-Match` is checked
+/// against, and of a hash of `body`, which also changes with what the viewer sees
+/// of the resource so that revalidated copies stay accurate.
+pub fn versioned_etag(version: &str, body: &JsonValue) -> String {
+    format!("\"{}-{}\"", version, body_hash(body))
+}
+
+/// Whether a list of entity tags, as found in `If-Match`, contains a tag of the
+/// given `version`. Weak tags never match since `If-Match` uses the strong comparison.
+pub fn version_matches(header: &str, version: &str) -> bool {
+    let prefix = format!("\"{}-", version);
+    header
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.starts_with(&prefix))
+}
+
+/// Responds with `body` along with its validators, or with `304 Not Modified` when
+/// the client's copy is still current. `etag` defaults to a hash of the body.
+/// `last_modified` is left out for authenticated requests, whose responses also
+/// change with the viewer's follows and favorites.
+pub fn respond<'r>(
+    req: &Request,
+    body: JsonValue,
+    etag: Option<String>,
+    last_modified: Option<NaiveDateTime>,
+) -> response::Result<'r> {
+    let etag = etag.unwrap_or_else(|| body_etag(&body));
+    let last_modified = last_modified.filter(|_| !is_authenticated(req));
+    let cacheable = req.method() == Method::Get;
+    let mut response = if cacheable && is_fresh(req, &etag, last_modified) {
+        Response::build().status(Status::NotModified).finalize()
+    } else {
+        body.respond_to(req)?
+    };
+    response.set_raw_header("ETag", etag);
+    if let Some(last_modified) = last_modified {
+        response.set_raw_header("Last-Modified", encode_http_date(last_modified));
+    }
+    if cacheable {
+        response.set_raw_header("Cache-Control", cache_control(req));
+        response.set_raw_header("Vary", "Authorization, Cookie");
+    }
+    Ok(response)
+}
+
+fn body_etag(body: &JsonValue) -> String {
+    format!("\"{}\"", body_hash(body))
+}
+
+fn body_hash(body: &JsonValue) -> String {
+    tokens::hash(&body.0.to_string())[..32].to_owned()
+}
+
+// If-None-Match takes precedence over If-Modified-Since and uses the weak comparison
+fn is_fresh(req: &Request, etag: &str, last_modified: Option<NaiveDateTime>) -> bool {
+    let headers = req.headers();
+    if let Some(header) = headers.get_one("If-None-Match") {
+        let etag = etag.trim_start_matches("W/");
+        return header
+            .split(',')
+            .map(|candidate| candidate.trim().trim_start_matches("W/"))
+            .any(|candidate| candidate == "*" || candidate == etag);
+    }
+    match (headers.get_one("If-Modified-Since").and_then(decode_http_date), last_modified) {
+        // HTTP dates have a one second resolution
+        (Some(since), Some(modified)) => {
+            modified.and_utc().timestamp() <= since.and_utc().timestamp()
+        }
+        _ => false,
+    }
+}
+
+// Responses to authenticated requests depend on the user (followed authors,
+// favorites) and must not end up in shared caches.
+fn cache_control(req: &Request) -> String {
+    if is_authenticated(req) {
+        "private, no-cache".to_owned()
+    } else {
+        format!("public, max-age={}", ANONYMOUS_MAX_AGE)
+    }
+}
+
+fn is_authenticated(req: &Request) -> bool {
+    req.headers().contains("Authorization") || req.cookies().get(SESSION_COOKIE).is_some()
+}
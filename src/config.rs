@@ -1,9 +1,87 @@
+use crate::jwt_keys::JwtKeys;
+use chrono::Duration;
 use rocket::config::{Environment, Value};
 use std::collections::HashMap;
 use std::env;
+use std::fmt::Display;
+use std::str::FromStr;
+
+const DEFAULT_ACCESS_TOKEN_LIFETIME: i64 = 15 * 60;
+const DEFAULT_REFRESH_TOKEN_LIFETIME: i64 = 30 * 24 * 60 * 60;
+const DEFAULT_PASSWORD_RESET_LIFETIME: i64 = 60 * 60;
+const DEFAULT_EMAIL_VERIFICATION_LIFETIME: i64 = 2 * 24 * 60 * 60;
+const DEFAULT_PASSWORD_MEMORY_COST: u32 = 19 * 1024;
+const DEFAULT_PASSWORD_TIME_COST: u32 = 2;
+const DEFAULT_PASSWORD_PARALLELISM: u32 = 1;
+const DEFAULT_ACCOUNT_LOGIN_THRESHOLD: i32 = 5;
+const DEFAULT_IP_LOGIN_THRESHOLD: i32 = 20;
+const DEFAULT_BASE_LOCKOUT: i64 = 30;
+const DEFAULT_MAX_LOCKOUT: i64 = 60 * 60;
+const DEFAULT_SLUG_MAX_LENGTH: usize = 80;
+const MIN_SLUG_MAX_LENGTH: usize = 32;
 
 pub struct Config {
-    pub secret: String,
+    pub jwt_keys: JwtKeys,
+    pub access_token_lifetime: Duration,
+    pub refresh_token_lifetime: Duration,
+    pub password_reset_lifetime: Duration,
+    pub email_verification_lifetime: Duration,
+    pub require_verified_email: bool,
+    pub session_cookie: bool,
+    /// Take the client address from `X-Real-IP`, only safe behind a reverse proxy setting it
+    pub trust_proxy_headers: bool,
+    /// Reject article updates and deletions that don't carry an `If-Match` header
+    pub require_if_match: bool,
+    pub app_url: String,
+    pub password_hashing: PasswordHashing,
+    pub login_throttling: LoginThrottling,
+    pub slugs: SlugOptions,
+    /// Verified emails of the users who are always admins
+    pub admin_emails: Vec<String>,
+}
+
+/// Argon2id costs used for new password hashes. Memory is expressed in KiB.
+pub struct PasswordHashing {
+    pub memory_cost: u32,
+    pub time_cost: u32,
+    pub parallelism: u32,
+}
+
+/// Failed logins tolerated before locking an account or a client address.
+/// Each failure past the threshold doubles the lockout, up to `max_lockout`.
+pub struct LoginThrottling {
+    pub account_threshold: i32,
+    pub ip_threshold: i32,
+    pub base_lockout: Duration,
+    pub max_lockout: Duration,
+}
+
+/// How article slugs are derived from titles. Titles are transliterated to ASCII,
+/// and `max_length` includes the date prefix and any suffix.
+pub struct SlugOptions {
+    pub strategy: SlugStrategy,
+    pub date_prefix: bool,
+    pub max_length: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SlugStrategy {
+    /// `my-title`, then `my-title-2`, `my-title-3`... when already taken
+    Clean,
+    /// `my-title-` followed by random characters
+    Random,
+}
+
+impl FromStr for SlugStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SlugStrategy, String> {
+        match s {
+            "clean" => Ok(SlugStrategy::Clean),
+            "random" => Ok(SlugStrategy::Random),
+            _ => Err(format!("unknown slug strategy {}, expected clean or random", s)),
+        }
+    }
 }
 
 impl Config {
@@ -15,7 +93,85 @@ impl Config {
                 Err(format!("SECRET_KEY environment variable required: {}", err))
             }
         })?;
-        Ok(Config { secret })
+        let jwt_keys = JwtKeys::from_env(&secret)?;
+        let access_token_lifetime = Duration::seconds(env_or(
+            "ACCESS_TOKEN_LIFETIME",
+            DEFAULT_ACCESS_TOKEN_LIFETIME,
+        )?);
+        let refresh_token_lifetime = Duration::seconds(env_or(
+            "REFRESH_TOKEN_LIFETIME",
+            DEFAULT_REFRESH_TOKEN_LIFETIME,
+        )?);
+        let password_reset_lifetime = Duration::seconds(env_or(
+            "PASSWORD_RESET_LIFETIME",
+            DEFAULT_PASSWORD_RESET_LIFETIME,
+        )?);
+        let email_verification_lifetime = Duration::seconds(env_or(
+            "EMAIL_VERIFICATION_LIFETIME",
+            DEFAULT_EMAIL_VERIFICATION_LIFETIME,
+        )?);
+        let require_verified_email = env_or("REQUIRE_VERIFIED_EMAIL", false)?;
+        let session_cookie = env_or("SESSION_COOKIE", false)?;
+        let trust_proxy_headers = env_or("TRUST_PROXY_HEADERS", false)?;
+        let require_if_match = env_or("REQUIRE_IF_MATCH", false)?;
+        let app_url = env_or("APP_URL", "http://localhost:8080".to_owned())?;
+        let password_hashing = PasswordHashing {
+            memory_cost: env_or("PASSWORD_MEMORY_COST", DEFAULT_PASSWORD_MEMORY_COST)?,
+            time_cost: env_or("PASSWORD_TIME_COST", DEFAULT_PASSWORD_TIME_COST)?,
+            parallelism: env_or("PASSWORD_PARALLELISM", DEFAULT_PASSWORD_PARALLELISM)?,
+        };
+        let login_throttling = LoginThrottling {
+            account_threshold: env_or("ACCOUNT_LOGIN_THRESHOLD", DEFAULT_ACCOUNT_LOGIN_THRESHOLD)?,
+            ip_threshold: env_or("IP_LOGIN_THRESHOLD", DEFAULT_IP_LOGIN_THRESHOLD)?,
+            base_lockout: Duration::seconds(env_or("BASE_LOGIN_LOCKOUT", DEFAULT_BASE_LOCKOUT)?),
+            max_lockout: Duration::seconds(env_or("MAX_LOGIN_LOCKOUT", DEFAULT_MAX_LOCKOUT)?),
+        };
+        let slugs = SlugOptions {
+            strategy: env_or("SLUG_STRATEGY", SlugStrategy::Clean)?,
+            date_prefix: env_or("SLUG_DATE_PREFIX", false)?,
+            max_length: env_or("SLUG_MAX_LENGTH", DEFAULT_SLUG_MAX_LENGTH)?,
+        };
+        if slugs.max_length < MIN_SLUG_MAX_LENGTH {
+            return Err(format!("SLUG_MAX_LENGTH must be at least {}", MIN_SLUG_MAX_LENGTH));
+        }
+        let admin_emails = env::var("ADMIN_EMAILS")
+            .map(|emails| {
+                emails
+                    .split(',')
+                    .map(|email| email.trim().to_owned())
+                    .filter(|email| !email.is_empty())
+                    .collect()
+            })
+            .unwrap_or(vec![]);
+        Ok(Config {
+            jwt_keys,
+            access_token_lifetime,
+            refresh_token_lifetime,
+            password_reset_lifetime,
+            email_verification_lifetime,
+            require_verified_email,
+            session_cookie,
+            trust_proxy_headers,
+            require_if_match,
+            app_url,
+            password_hashing,
+            login_throttling,
+            slugs,
+            admin_emails,
+        })
+    }
+}
+
+fn env_or<T>(name: &str, default: T) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match env::var(name) {
+        Ok(value) => value
+            .parse::<T>()
+            .map_err(|err| format!("{} parsing failed: {}", name, err)),
+        Err(_) => Ok(default),
     }
 }
 
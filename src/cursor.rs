@@ -0,0 +1,183 @@
+use crate::db::DbResult;
+use crate::errors::Error;
+use chrono::NaiveDateTime;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Position in a listing, handed to clients as an opaque token. Listings are
+/// ordered by `rank`, which is 0 unless they are sorted by a count, then by `time`
+/// and finally by `key`, which breaks ties. `backwards` cursors lead to the items
+/// before the position.
+#[derive(Serialize, Deserialize)]
+pub struct Cursor<K> {
+    #[serde(rename = "r", default)]
+    pub rank: i64,
+    #[serde(rename = "t")]
+    pub time: NaiveDateTime,
+    #[serde(rename = "k")]
+    pub key: K,
+    #[serde(rename = "b", default)]
+    pub backwards: bool,
+}
+
+impl<K: Serialize + DeserializeOwned> Cursor<K> {
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        base64::encode_config(json, base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Reads the `cursor` query parameter, if any.
+    pub fn parse(token: Option<String>) -> DbResult<Option<Cursor<K>>> {
+        token
+            .map(|token| {
+                base64::decode_config(&token, base64::URL_SAFE_NO_PAD)
+                    .ok()
+                    .and_then(|json| serde_json::from_slice(&json).ok())
+                    .ok_or(Error::ValidationFailed(json![{"cursor": "is invalid"}]))
+            })
+            .transpose()
+    }
+}
+
+/// Items of a page along with the cursors to the pages around it.
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+}
+
+/// Builds a page out of rows fetched with one more than `limit`, which tells
+/// whether there is a page further in the direction of `cursor`. `first` is set
+/// for the first page of offset based listings, which has nothing before it.
+pub fn paginate<T, K, F>(
+    mut rows: Vec<T>,
+    limit: i32,
+    cursor: &Option<Cursor<K>>,
+    first: bool,
+    position: F,
+) -> Page<T>
+where
+    K: Serialize + DeserializeOwned,
+    F: Fn(&T) -> (i64, NaiveDateTime, K),
+{
+    let backwards = cursor.as_ref().map_or(false, |c| c.backwards);
+    let more = rows.len() > limit as usize;
+    if more && backwards {
+        // Rows come in listing order, the extra one is the farthest from the cursor
+        rows.remove(0);
+    } else if more {
+        rows.truncate(limit as usize);
+    }
+    let (has_prev, has_next) = if backwards {
+        (more, true)
+    } else {
+        (!first, more)
+    };
+    let at = |row: &T, backwards: bool| {
+        let (rank, time, key) = position(row);
+        Cursor {
+            rank,
+            time,
+            key,
+            backwards,
+        }
+        .encode()
+    };
+    Page {
+        next_cursor: rows.last().filter(|_| has_next).map(|row| at(row, false)),
+        prev_cursor: rows.first().filter(|_| has_prev).map(|row| at(row, true)),
+        items: rows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn time(day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, day)
+            .and_then(|date| date.and_hms_opt(12, 0, 0))
+            .unwrap()
+    }
+
+    fn cursor(day: u32, backwards: bool) -> Cursor<String> {
+        Cursor {
+            rank: 3,
+            time: time(day),
+            key: format!("article-{}", day),
+            backwards,
+        }
+    }
+
+    fn decode(token: &str) -> Cursor<String> {
+        Cursor::parse(Some(token.to_owned()))
+            .ok()
+            .flatten()
+            .unwrap()
+    }
+
+    fn page(rows: Vec<u32>, cursor: &Option<Cursor<String>>, first: bool) -> Page<u32> {
+        paginate(rows, 2, cursor, first, |day| {
+            (0, time(*day), format!("article-{}", day))
+        })
+    }
+
+    #[test]
+    fn decodes_encoded_cursors() {
+        for backwards in [false, true].iter() {
+            let decoded = decode(&cursor(5, *backwards).encode());
+            assert_eq!(decoded.rank, 3);
+            assert_eq!(decoded.time, time(5));
+            assert_eq!(decoded.key, "article-5");
+            assert_eq!(decoded.backwards, *backwards);
+        }
+        assert!(Cursor::<String>::parse(None).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        let not_json = base64::encode_config("not json", base64::URL_SAFE_NO_PAD);
+        let wrong_key = base64::encode_config(
+            r#"{"t":"2026-10-18T12:00:00","k":1}"#,
+            base64::URL_SAFE_NO_PAD,
+        );
+        for token in ["", "%%%", "not base64!", &not_json, &wrong_key].iter() {
+            assert!(
+                Cursor::<String>::parse(Some(token.to_string())).is_err(),
+                "{}",
+                token
+            );
+        }
+    }
+
+    #[test]
+    fn trims_the_extra_row() {
+        let first = page(vec![1, 2, 3], &None, true);
+        assert_eq!(first.items, vec![1, 2]);
+        assert_eq!(decode(first.next_cursor.as_ref().unwrap()).key, "article-2");
+        assert!(first.prev_cursor.is_none());
+
+        let last = page(vec![3, 4], &Some(cursor(2, false)), false);
+        assert_eq!(last.items, vec![3, 4]);
+        assert!(last.next_cursor.is_none());
+        let prev = decode(last.prev_cursor.as_ref().unwrap());
+        assert_eq!((prev.key.as_str(), prev.backwards), ("article-3", true));
+    }
+
+    #[test]
+    fn trims_backwards_pages_from_the_start() {
+        let middle = page(vec![1, 2, 3], &Some(cursor(4, true)), false);
+        assert_eq!(middle.items, vec![2, 3]);
+        assert!(middle.prev_cursor.is_some());
+        assert_eq!(
+            decode(middle.next_cursor.as_ref().unwrap()).key,
+            "article-3"
+        );
+
+        let start = page(vec![1, 2], &Some(cursor(3, true)), false);
+        assert_eq!(start.items, vec![1, 2]);
+        assert!(start.prev_cursor.is_none());
+        assert!(start.next_cursor.is_some());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::authentication::AuthData;
+use crate::db::{DbConnection, DbResult};
+use crate::errors::Error;
+use crate::models::api_key::{ApiKey, ApiKeyList, ApiKeyQuery};
+use crate::schema::{api_keys, users};
+use crate::tokens;
+use ammonia;
+use diesel::prelude::*;
+
+/// Distinguishes API keys from JWTs in the `Authorization` header.
+pub const KEY_PREFIX: &str = "cdt_";
+const DISPLAYED_PREFIX_LEN: usize = 12;
+
+pub fn create(
+    conn: &DbConnection,
+    user_id: i32,
+    name: &String,
+    scopes: &Option<Vec<String>>,
+) -> DbResult<ApiKey> {
+    let key = format!("{}{}", KEY_PREFIX, tokens::generate());
+    diesel::insert_into(api_keys::table)
+        .values((
+            api_keys::user_id.eq(user_id),
+            api_keys::name.eq(ammonia::clean(name)),
+            api_keys::prefix.eq(&key[..DISPLAYED_PREFIX_LEN]),
+            api_keys::key_hash.eq(tokens::hash(&key)),
+            api_keys::scopes.eq(scopes),
+            api_keys::created_at.eq(diesel::dsl::now),
+        ))
+        .returning(columns())
+        .get_result::<ApiKeyQuery>(conn)
+        .map(|created| created.to_api_key(Some(key)))
+        .map_err(Into::into)
+}
+
+pub fn list(conn: &DbConnection, user_id: i32) -> DbResult<ApiKeyList> {
+    api_keys::table
+        .filter(
+            api_keys::user_id
+                .eq(user_id)
+                .and(api_keys::revoked_at.is_null()),
+        )
+        .order(api_keys::created_at.desc())
+        .select(columns())
+        .get_results::<ApiKeyQuery>(conn)
+        .map(|keys| ApiKeyList(keys.into_iter().map(|k| k.to_api_key(None)).collect()))
+        .map_err(Into::into)
+}
+
+pub fn revoke(conn: &DbConnection, user_id: i32, key_id: i32) -> DbResult<()> {
+    diesel::update(
+        api_keys::table.filter(
+            api_keys::id
+                .eq(key_id)
+                .and(api_keys::user_id.eq(user_id))
+                .and(api_keys::revoked_at.is_null()),
+        ),
+    )
+    .set(api_keys::revoked_at.eq(diesel::dsl::now))
+    .execute(conn)
+    .map_err(Into::<Error>::into)
+    .and_then(|revoked| {
+        if revoked == 1 {
+            Ok(())
+        } else {
+            Err(Error::DatabaseError(diesel::result::Error::NotFound))
+        }
+    })
+}
+
+/// Resolves an API key into the identity of its owner, restricted to the key's scopes.
+pub fn authenticate(conn: &DbConnection, key: &str) -> Option<AuthData> {
+    let (key_id, scopes, owner, username, token_version): (
+        i32,
+        Option<Vec<String>>,
+        i32,
+        String,
+        i32,
+    ) = api_keys::table
+        .inner_join(users::table)
+        .filter(
+            api_keys::key_hash
+                .eq(tokens::hash(key))
+                .and(api_keys::revoked_at.is_null())
+                .and(users::suspended_at.is_null()),
+        )
+        .select((
+            api_keys::id,
+            api_keys::scopes,
+            users::id,
+            users::username,
+            users::token_version,
+        ))
+        .get_result(conn)
+        .ok()?;
+    diesel::update(api_keys::table.filter(api_keys::id.eq(key_id)))
+        .set(api_keys::last_used_at.eq(diesel::dsl::now))
+        .execute(conn)
+        .ok()?;
+    Some(AuthData {
+        id: owner,
+        username,
+        exp: 0,
+        jti: String::new(),
+        ver: token_version,
+        api_key: Some(key_id),
+        scopes,
+    })
+}
+
+fn columns() -> (
+    api_keys::id,
+    api_keys::name,
+    api_keys::prefix,
+    api_keys::scopes,
+    api_keys::created_at,
+    api_keys::last_used_at,
+) {
+    (
+        api_keys::id,
+        api_keys::name,
+        api_keys::prefix,
+        api_keys::scopes,
+        api_keys::created_at,
+        api_keys::last_used_at,
+    )
+}
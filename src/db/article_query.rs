@@ -8,6 +8,8 @@ pub type ArticleQuerySql = (
     Timestamptz,
     Timestamptz,
     Text,
+    Nullable<Timestamptz>,
+    Text,
     Nullable<Text>,
     Nullable<Text>,
     Nullable<Array<Text>>,
@@ -1,19 +1,40 @@
+use super::audit;
 use super::get_articles::*;
+use super::limits::{coerce_limit, coerce_offset};
+use super::moderation;
+use super::revisions;
+use super::search_articles::*;
 use super::select_article_by_slug::*;
-use super::tags::{get_tags, Tag};
+use super::slug_history;
+use super::tags::{get_tags, search_tags, Tag};
 use super::user_feed::*;
+use crate::conditional;
+use crate::config::{SlugOptions, SlugStrategy};
+use crate::cursor::{paginate, Cursor};
 use crate::db::{DbConnection, DbResult};
 use crate::errors;
+use crate::format::encode_datetime;
 use crate::models::article::{
-    slugify, Article, ArticleList, NewArticleData, PGArticle, TagList, UpdateArticleData,
+    numbered_slug, slugify, Article, ArticleFilter, ArticleList, ArticleMatch, ArticleMatchList,
+    ArticleSort, ArticleStatus, Highlight, NewArticleData, PGArticle, TagList, UpdateArticleData,
+    RESERVED_SLUGS,
 };
 use crate::models::user::{Profile, User};
 use crate::schema;
 use ammonia;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime, Utc};
 use diesel::prelude::*;
+use diesel::result::DatabaseErrorKind;
 use diesel::sql_types::*;
 use errors::Error;
+use serde_json::Value;
+use std::net::IpAddr;
+
+// Slugs checked at once when looking for a free one
+const SLUG_CANDIDATES: u32 = 10;
+// Tries to save an article under a free slug before giving up
+const SLUG_ATTEMPTS: u32 = 3;
+const SLUG_CONSTRAINT: &str = "articles_slug_key";
 
 #[derive(Queryable, QueryableByName)]
 struct ArticleQuery {
@@ -30,6 +51,10 @@ struct ArticleQuery {
     #[sql_type = "Timestamptz"]
     article_update: NaiveDateTime,
     #[sql_type = "Text"]
+    article_status: String,
+    #[sql_type = "Nullable<Timestamptz>"]
+    article_publication: Option<NaiveDateTime>,
+    #[sql_type = "Text"]
     author_username: String,
     #[sql_type = "Nullable<Text>"]
     author_bio: Option<String>,
@@ -46,36 +71,105 @@ struct ArticleQuery {
     #[sql_type = "BigInt"]
     total_articles: i64,
 }
+#[derive(QueryableByName)]
+struct ListedArticleQuery {
+    #[diesel(embed)]
+    article: ArticleQuery,
+    #[sql_type = "BigInt"]
+    sort_rank: i64,
+    #[sql_type = "Timestamptz"]
+    sort_time: NaiveDateTime,
+}
+
+#[derive(QueryableByName)]
+struct SearchResultQuery {
+    #[diesel(embed)]
+    article: ArticleQuery,
+    #[sql_type = "Text"]
+    title_highlight: String,
+    #[sql_type = "Text"]
+    description_highlight: String,
+    #[sql_type = "Text"]
+    body_highlight: String,
+}
+
 pub fn articles(
     conn: &DbConnection,
-    m_tag: Option<String>,
-    m_author: Option<String>,
+    filter: ArticleFilter,
     m_offset: Option<i32>,
     m_limit: Option<i32>,
-    m_favorited: Option<String>,
     current_user: Option<i32>,
+    sort: ArticleSort,
+    cursor: Option<Cursor<String>>,
 ) -> DbResult<ArticleList> {
-    get_articles(
+    let m_offset = m_offset.filter(|_| cursor.is_none());
+    conn.query_by_name(&get_articles(
         m_limit,
         m_offset,
         current_user,
-        m_tag,
-        m_favorited,
-        m_author,
-    )
-    .load(conn)
+        filter,
+        None,
+        sort,
+        cursor.as_ref(),
+    ))
     .map_err(Into::into)
-    .map(|v: Vec<ArticleQuery>| ArticleList {
-        article_count: (&v).first().map(|x| x.total_articles).unwrap_or(0),
-        articles: v.into_iter().map(from_article_query).collect::<Vec<_>>(),
-    })
+    .map(|v: Vec<ListedArticleQuery>| article_list(v, m_limit, m_offset, &cursor))
 }
 
-pub fn tags(conn: &DbConnection) -> DbResult<TagList> {
-    get_tags(20)
-        .get_results(conn)
+/// Unpublished drafts of the user, who is the only one allowed to see them.
+pub fn drafts(
+    conn: &DbConnection,
+    user_id: i32,
+    limit: Option<i32>,
+    offset: Option<i32>,
+) -> DbResult<ArticleList> {
+    conn.query_by_name(&get_articles(
+        limit,
+        offset,
+        Some(user_id),
+        ArticleFilter::default(),
+        Some(ArticleStatus::Draft.as_str().to_owned()),
+        ArticleSort::Newest,
+        None,
+    ))
+    .map_err(Into::into)
+    .map(|v: Vec<ListedArticleQuery>| article_list(v, limit, offset, &None))
+}
+
+/// Full-text search among the articles the user can see, best matches first.
+pub fn search(
+    conn: &DbConnection,
+    query: String,
+    current_user: Option<i32>,
+    limit: Option<i32>,
+    offset: Option<i32>,
+) -> DbResult<ArticleMatchList> {
+    conn.query_by_name(&search_articles(query, limit, offset, current_user))
         .map_err(Into::into)
-        .map(|tags: Vec<Tag>| TagList(tags.into_iter().map(|tag| tag.tag).collect::<Vec<_>>()))
+        .map(|v: Vec<SearchResultQuery>| ArticleMatchList {
+            article_count: v.first().map(|x| x.article.total_articles).unwrap_or(0),
+            articles: v
+                .into_iter()
+                .map(|result| ArticleMatch {
+                    article: from_article_query(result.article),
+                    highlight: Highlight {
+                        title: result.title_highlight,
+                        description: result.description_highlight,
+                        body: result.body_highlight,
+                    },
+                })
+                .collect::<Vec<_>>(),
+        })
+}
+
+/// Most used tags, or those matching a partial or misspelled `search`.
+pub fn tags(conn: &DbConnection, search: Option<String>, limit: Option<i32>) -> DbResult<TagList> {
+    match search {
+        Some(search) => search_tags(search, limit).get_results(conn),
+        None => get_tags(limit.unwrap_or(20)).get_results(conn),
+    }
+    .map_err(Into::into)
+    .map(|tags: Vec<Tag>| TagList(tags.into_iter().map(|tag| tag.tag).collect::<Vec<_>>()))
 }
 
 pub fn article(
@@ -86,7 +180,13 @@ pub fn article(
     get_by_slug(conn, current_user, search)
 }
 
-pub fn create(conn: &DbConnection, article: &NewArticleData, user_id: i32) -> DbResult<Article> {
+pub fn create(
+    conn: &DbConnection,
+    article: &NewArticleData,
+    user_id: i32,
+    slugs: &SlugOptions,
+    ip: Option<IpAddr>,
+) -> DbResult<Article> {
     use schema::articles::dsl::*;
     use schema::users;
     let profile: Profile = users::table
@@ -95,26 +195,64 @@ pub fn create(conn: &DbConnection, article: &NewArticleData, user_id: i32) -> Db
         .map_err(Into::<Error>::into)
         .map(|u: User| u.to_profile(false))?;
 
-    let pg_article: PGArticle = diesel::insert_into(articles)
-        .values((
-            slug.eq(slugify(&article.title)),
-            title.eq(&ammonia::clean(&article.title)),
-            description.eq(&ammonia::clean(&article.description)),
-            body.eq(&ammonia::clean(&article.body)),
-            created_at.eq(diesel::dsl::now),
-            updated_at.eq(diesel::dsl::now),
-            author.eq(user_id),
-        ))
-        .get_result(conn)
-        .map_err(Into::<Error>::into)?;
+    let new_status = article
+        .status
+        .as_deref()
+        .unwrap_or(ArticleStatus::Published.as_str());
+    let publication = match article.publish_at {
+        Some(date) => Some(date.naive_utc()),
+        None if new_status != ArticleStatus::Draft.as_str() => Some(Utc::now().naive_utc()),
+        None => None,
+    };
+    with_free_slug(conn, || {
+        let today = Utc::now().naive_utc().date();
+        let new_slug = unique_slug(conn, &article.title, today, slugs, None)?;
+        let pg_article: PGArticle = diesel::insert_into(articles)
+            .values((
+                slug.eq(new_slug),
+                title.eq(&ammonia::clean(&article.title)),
+                description.eq(&ammonia::clean(&article.description)),
+                body.eq(&ammonia::clean(&article.body)),
+                created_at.eq(diesel::dsl::now),
+                updated_at.eq(diesel::dsl::now),
+                author.eq(user_id),
+                status.eq(new_status),
+                published_at.eq(publication),
+            ))
+            .get_result(conn)
+            .map_err(Into::<Error>::into)?;
 
-    let tag_list: Vec<String> = article
-        .tag_list
-        .clone()
-        .map(|tag_list| -> DbResult<Vec<String>> { update_tags(tag_list, pg_article.id, conn) })
-        .unwrap_or(Ok(vec![]))?;
+        let tag_list: Vec<String> = article
+            .tag_list
+            .clone()
+            .map(|tag_list| -> DbResult<Vec<String>> {
+                update_tags(tag_list, pg_article.id, conn)
+            })
+            .unwrap_or(Ok(vec![]))?;
+
+        let art_id = pg_article.id;
+        revisions::record(conn, art_id, user_id)?;
+        let created = pg_article.to_article(profile.clone(), tag_list, false);
+        audit::record(
+            conn,
+            user_id,
+            ip,
+            "create_article",
+            "article",
+            &art_id.to_string(),
+            audit::diff(&Value::Null, &snapshot(&created)),
+        )?;
+        Ok(created)
+    })
+}
 
-    Ok(pg_article.to_article(profile, tag_list, false))
+// Replaces the tags of the article, returning them as they were normalized
+fn set_tags(conn: &DbConnection, article_id: i32, tag_list: Vec<String>) -> DbResult<Vec<String>> {
+    use schema::article_tag_associations as atas;
+    diesel::delete(atas::table.filter(atas::article_id.eq(article_id)))
+        .execute(conn)
+        .map_err(Into::<Error>::into)?;
+    update_tags(tag_list, article_id, conn)
 }
 
 fn update_tags(
@@ -164,24 +302,51 @@ fn update_tags(
     Ok(correct_tags)
 }
 
-pub fn delete(conn: &DbConnection, user_id: i32, to_delete: String) -> DbResult<Article> {
+/// Deletes an article owned by the user, or any article when `moderate` is set.
+/// `if_match` is the `If-Match` header the client sent, if any.
+pub fn delete(
+    conn: &DbConnection,
+    user_id: i32,
+    to_delete: String,
+    moderate: bool,
+    if_match: &Option<String>,
+    ip: Option<IpAddr>,
+) -> DbResult<Article> {
     use schema::articles::dsl::*;
     let (author_id, art_id): (i32, i32) = articles
         .select((author, id))
         .filter(slug.eq(&to_delete))
         .get_result(conn)
         .map_err(Into::<Error>::into)?;
-    if author_id != user_id {
-        return Err(Error::Unauthorized);
+    if author_id != user_id && !moderate {
+        return Err(Error::Forbidden);
     }
-    let artcl = get_by_slug(conn, Some(user_id), to_delete)?;
-    diesel::delete(articles)
-        .filter(id.eq(art_id))
-        .execute(conn)
-        .map_err(Into::<Error>::into)?;
-    Ok(artcl)
+    conn.transaction(|| {
+        lock(conn, art_id)?;
+        // Seen as the author, since moderators can't see drafts or scheduled articles
+        let artcl = get_by_slug(conn, Some(author_id), to_delete.clone())?;
+        check_precondition(&artcl, if_match)?;
+        diesel::delete(articles)
+            .filter(id.eq(art_id))
+            .execute(conn)
+            .map_err(Into::<Error>::into)?;
+        if author_id != user_id {
+            moderation::record(conn, user_id, author_id, "delete_article", &to_delete)?;
+        }
+        audit::record(
+            conn,
+            user_id,
+            ip,
+            "delete_article",
+            "article",
+            &art_id.to_string(),
+            audit::diff(&snapshot(&artcl), &Value::Null),
+        )?;
+        Ok(artcl)
+    })
 }
 
+use schema::article_slug_history;
 use schema::articles;
 #[derive(AsChangeset)]
 #[table_name = "articles"]
@@ -190,51 +355,261 @@ struct ChangeArticle {
     title: Option<String>,
     description: Option<String>,
     body: Option<String>,
+    status: Option<String>,
+    published_at: Option<NaiveDateTime>,
 }
 
+/// Changes to the article at `slug`, made by `editor`. `moderate` lets them edit
+/// articles of others, `if_match` is the `If-Match` header the client sent, if any.
+pub struct ArticleEdit<'a> {
+    pub editor: i32,
+    pub slug: String,
+    pub data: &'a UpdateArticleData,
+    pub moderate: bool,
+    pub if_match: &'a Option<String>,
+}
+
+/// Updates an article owned by the user, or any article when `moderate` is set.
 pub fn update(
     conn: &DbConnection,
-    user_id: i32,
-    to_update: String,
-    data: &UpdateArticleData,
+    edit: ArticleEdit,
+    slugs: &SlugOptions,
+    ip: Option<IpAddr>,
 ) -> DbResult<Article> {
+    apply_edit(conn, edit, slugs, ip, "update_article")
+}
+
+/// Applies the edit like `update`, auditing it as `action`.
+pub fn apply_edit(
+    conn: &DbConnection,
+    edit: ArticleEdit,
+    slugs: &SlugOptions,
+    ip: Option<IpAddr>,
+    action: &str,
+) -> DbResult<Article> {
+    let ArticleEdit {
+        editor: user_id,
+        slug: to_update,
+        data,
+        moderate,
+        if_match,
+    } = edit;
     use schema::articles::dsl::*;
-    let (art_id, art_title): (i32, String) = articles
-        .filter(slug.eq(&to_update).and(author.eq(user_id)))
-        .select((id, title))
+    let (art_id, art_title, author_id, art_publication, art_creation): (
+        i32,
+        String,
+        i32,
+        Option<NaiveDateTime>,
+        NaiveDateTime,
+    ) = articles
+        .filter(slug.eq(&to_update))
+        .select((id, title, author, published_at, created_at))
         .get_result(conn)
         .optional()
         .map_err(Into::<Error>::into)
-        .and_then(|art: Option<(i32, String)>| match art {
-            Some(r) => Ok(r),
-            None => Err(Error::Forbidden),
-        })?;
-    let new_slug = data.title.as_ref().and_then(|a| {
-        let t = ammonia::clean(&a);
-        if t == art_title {
-            None
-        } else {
-            Some(slugify(&a))
-        }
-    });
-    diesel::update(articles)
-        .filter(id.eq(art_id))
-        .set((
-            ChangeArticle {
-                slug: new_slug.clone(),
-                title: data.title.clone().map(|a| ammonia::clean(&a)),
-                description: data.description.clone().map(|a| ammonia::clean(&a)),
-                body: data.body.clone().map(|a| ammonia::clean(&a)),
+        .and_then(
+            |art: Option<(i32, String, i32, Option<NaiveDateTime>, NaiveDateTime)>| match art {
+                Some(r) if r.2 == user_id || moderate => Ok(r),
+                _ => Err(Error::Forbidden),
             },
-            updated_at.eq(diesel::dsl::now),
-        ))
-        .execute(conn)
-        .map_err(Into::<Error>::into)?;
+        )?;
+    // Articles leaving the draft state without a date are published right away
+    let publication = match (data.publish_at, &data.status) {
+        (Some(date), _) => Some(date.naive_utc()),
+        (None, Some(new_status))
+            if new_status != ArticleStatus::Draft.as_str() && art_publication.is_none() =>
+        {
+            Some(Utc::now().naive_utc())
+        }
+        _ => None,
+    };
+    with_free_slug(conn, || {
+        lock(conn, art_id)?;
+        let before = get_by_slug(conn, Some(author_id), to_update.clone())?;
+        check_precondition(&before, if_match)?;
+        let new_slug = match &data.title {
+            Some(t) if ammonia::clean(t) != art_title => {
+                let generated = unique_slug(conn, t, art_creation.date(), slugs, Some(art_id))?;
+                // Titles differing only by case or punctuation keep their slug
+                Some(generated).filter(|generated| generated != &to_update)
+            }
+            _ => None,
+        };
+        diesel::update(articles)
+            .filter(id.eq(art_id))
+            .set((
+                ChangeArticle {
+                    slug: new_slug.clone(),
+                    title: data.title.clone().map(|a| ammonia::clean(&a)),
+                    description: data.description.clone().map(|a| ammonia::clean(&a)),
+                    body: data.body.clone().map(|a| ammonia::clean(&a)),
+                    status: data.status.clone(),
+                    published_at: publication,
+                },
+                updated_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)
+            .map_err(Into::<Error>::into)?;
 
-    data.tag_list
-        .clone()
-        .map(|tag_list| -> DbResult<Vec<String>> { update_tags(tag_list, art_id, conn) });
-    get_by_slug(conn, Some(user_id), new_slug.unwrap_or(to_update))
+        if let Some(new_slug) = &new_slug {
+            slug_history::record(conn, art_id, &to_update, new_slug)?;
+        }
+        if let Some(tag_list) = &data.tag_list {
+            set_tags(conn, art_id, tag_list.clone())?;
+        }
+        if author_id != user_id {
+            moderation::record(conn, user_id, author_id, action, &to_update)?;
+        }
+        let edited = data.title.is_some() || data.description.is_some() || data.body.is_some();
+        if edited || data.tag_list.is_some() {
+            revisions::record(conn, art_id, user_id)?;
+        }
+        let after = get_by_slug(conn, Some(author_id), new_slug.unwrap_or(to_update.clone()))?;
+        audit::record(
+            conn,
+            user_id,
+            ip,
+            action,
+            "article",
+            &art_id.to_string(),
+            audit::diff(&snapshot(&before), &snapshot(&after)),
+        )?;
+        Ok(after)
+    })
+}
+
+// Holds the article row until the end of the transaction, so that the
+// precondition can't be invalidated by a concurrent update
+fn lock(conn: &DbConnection, art_id: i32) -> DbResult<()> {
+    articles::table
+        .filter(articles::id.eq(art_id))
+        .select(articles::id)
+        .for_update()
+        .get_result::<i32>(conn)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+// Runs `change` in a transaction, again when the slug it picked was taken by a
+// concurrent one in the meantime
+fn with_free_slug<T, F>(conn: &DbConnection, change: F) -> DbResult<T>
+where
+    F: Fn() -> DbResult<T>,
+{
+    let mut attempt = 1;
+    loop {
+        match conn.transaction(&change) {
+            Err(Error::DatabaseError(diesel::result::Error::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                ref info,
+            ))) if info.constraint_name() == Some(SLUG_CONSTRAINT) && attempt < SLUG_ATTEMPTS => {
+                attempt += 1
+            }
+            result => return result,
+        }
+    }
+}
+
+// First free slug for the title, `article` being the one it is for if it already exists.
+// Former slugs of other articles are taken too, so that they keep redirecting.
+fn unique_slug(
+    conn: &DbConnection,
+    article_title: &str,
+    date: NaiveDate,
+    options: &SlugOptions,
+    article: Option<i32>,
+) -> DbResult<String> {
+    let candidate = slugify(article_title, date, options);
+    if options.strategy == SlugStrategy::Random {
+        return Ok(candidate);
+    }
+    let mut first = 1;
+    loop {
+        let candidates = (first..first + SLUG_CANDIDATES)
+            .map(|n| match n {
+                1 => candidate.clone(),
+                n => numbered_slug(&candidate, n, options.max_length),
+            })
+            .collect::<Vec<_>>();
+        let mut taken: Vec<(i32, String)> = articles::table
+            .filter(articles::slug.eq_any(&candidates))
+            .select((articles::id, articles::slug))
+            .load(conn)
+            .map_err(Into::<Error>::into)?;
+        taken.extend(
+            article_slug_history::table
+                .filter(article_slug_history::slug.eq_any(&candidates))
+                .select((article_slug_history::article_id, article_slug_history::slug))
+                .load::<(i32, String)>(conn)
+                .map_err(Into::<Error>::into)?,
+        );
+        let free = candidates.into_iter().find(|c| {
+            !RESERVED_SLUGS.contains(&c.as_str())
+                && !taken
+                    .iter()
+                    .any(|(owner, slug)| slug == c && Some(*owner) != article)
+        });
+        match free {
+            Some(free) => return Ok(free),
+            None => first += SLUG_CANDIDATES,
+        }
+    }
+}
+
+fn check_precondition(article: &Article, if_match: &Option<String>) -> DbResult<()> {
+    match if_match {
+        Some(header) if !conditional::version_matches(header, &article.version()) => {
+            Err(Error::PreconditionFailed)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Makes a draft, unlisted or scheduled article visible to everyone right away.
+/// Only its author can publish it.
+pub fn publish(
+    conn: &DbConnection,
+    user_id: i32,
+    to_publish: String,
+    ip: Option<IpAddr>,
+) -> DbResult<Article> {
+    use schema::articles::dsl::*;
+    let (art_id, author_id, art_publication): (i32, i32, Option<NaiveDateTime>) = articles
+        .filter(slug.eq(&to_publish))
+        .select((id, author, published_at))
+        .get_result(conn)
+        .map_err(Into::<Error>::into)?;
+    if author_id != user_id {
+        return Err(Error::Forbidden);
+    }
+    let now = Utc::now().naive_utc();
+    let publication = match art_publication {
+        Some(date) if date <= now => date,
+        _ => now,
+    };
+    conn.transaction(|| {
+        let before = get_by_slug(conn, Some(user_id), to_publish.clone())?;
+        diesel::update(articles)
+            .filter(id.eq(art_id))
+            .set((
+                status.eq(ArticleStatus::Published.as_str()),
+                published_at.eq(publication),
+                updated_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)
+            .map_err(Into::<Error>::into)?;
+        let after = get_by_slug(conn, Some(user_id), to_publish)?;
+        audit::record(
+            conn,
+            user_id,
+            ip,
+            "publish_article",
+            "article",
+            &art_id.to_string(),
+            audit::diff(&snapshot(&before), &snapshot(&after)),
+        )?;
+        Ok(after)
+    })
 }
 
 pub fn favorite(conn: &DbConnection, favoriter: i32, fav: &String) -> DbResult<Article> {
@@ -305,19 +680,50 @@ pub fn user_feed(
     user_id: i32,
     limit: Option<i32>,
     offset: Option<i32>,
+    cursor: Option<Cursor<String>>,
 ) -> DbResult<ArticleList> {
-    user_feed_query(limit, offset, user_id)
+    let offset = offset.filter(|_| cursor.is_none());
+    user_feed_query(limit, offset, user_id, cursor.as_ref())
         .get_results::<ArticleQuery>(conn)
-        .map(|v| ArticleList {
-            article_count: (&v).first().map(|x| x.total_articles).unwrap_or(0),
-            articles: v
+        .map(|v| {
+            // Feeds are only sorted by publication date
+            let listed = v
                 .into_iter()
-                .map(from_article_query)
-                .collect::<Vec<Article>>(),
+                .map(|aq| ListedArticleQuery {
+                    sort_rank: 0,
+                    sort_time: aq.article_publication.unwrap_or(aq.article_creation),
+                    article: aq,
+                })
+                .collect();
+            article_list(listed, limit, offset, &cursor)
         })
         .map_err(Into::<Error>::into)
 }
 
+// Rows are fetched with one more than the page, see `paginate`
+fn article_list(
+    rows: Vec<ListedArticleQuery>,
+    limit: Option<i32>,
+    offset: Option<i32>,
+    cursor: &Option<Cursor<String>>,
+) -> ArticleList {
+    let article_count = rows.first().map(|x| x.article.total_articles).unwrap_or(0);
+    let first = cursor.is_none() && coerce_offset(offset) == 0;
+    let page = paginate(rows, coerce_limit(limit), cursor, first, |row| {
+        (row.sort_rank, row.sort_time, row.article.article_slug.clone())
+    });
+    ArticleList {
+        article_count,
+        articles: page
+            .items
+            .into_iter()
+            .map(|row| from_article_query(row.article))
+            .collect(),
+        next_cursor: page.next_cursor,
+        prev_cursor: page.prev_cursor,
+    }
+}
+
 fn get_by_slug(
     conn: &DbConnection,
     current_user: Option<i32>,
@@ -329,6 +735,20 @@ fn get_by_slug(
         .map(from_article_query)
 }
 
+// Audited fields of an article
+fn snapshot(article: &Article) -> Value {
+    json!({
+        "slug": article.slug,
+        "title": article.title,
+        "description": article.description,
+        "body": article.body,
+        "tagList": article.tag_list,
+        "status": article.status,
+        "publishedAt": article.published_at,
+    })
+    .0
+}
+
 fn from_article_query(aq: ArticleQuery) -> Article {
     Article {
         author: Profile {
@@ -344,6 +764,8 @@ fn from_article_query(aq: ArticleQuery) -> Article {
         created_at: format! {"{:}", aq.article_creation},
         updated_at: format!["{:?}", aq.article_update],
         tag_list: aq.tags.unwrap_or(vec![]),
+        status: aq.article_status,
+        published_at: aq.article_publication.map(encode_datetime),
         favorited: aq.is_favorite,
         favorites_count: aq.favorites_count,
     }
@@ -0,0 +1,96 @@
+use crate::db::limits;
+use crate::db::{DbConnection, DbResult};
+use crate::errors::Error;
+use crate::models::audit::{AuditEventList, AuditEventQuery, AuditFilter};
+use crate::schema::audit_events;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use serde_json::{Map, Value};
+use std::net::IpAddr;
+
+/// Appends an event to the audit log. `changes` is usually built with `diff`.
+pub fn record(
+    conn: &DbConnection,
+    actor_id: i32,
+    ip: Option<IpAddr>,
+    action: &str,
+    target_type: &str,
+    target_id: &str,
+    changes: Value,
+) -> DbResult<()> {
+    diesel::insert_into(audit_events::table)
+        .values((
+            audit_events::actor_id.eq(actor_id),
+            audit_events::action.eq(action),
+            audit_events::target_type.eq(target_type),
+            audit_events::target_id.eq(target_id),
+            audit_events::ip.eq(ip.map(|ip| ip.to_string())),
+            audit_events::diff.eq(changes),
+            audit_events::created_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+/// Fields that differ between two JSON objects, as `{"field": {"from": .., "to": ..}}`.
+/// Pass `Value::Null` as `before` for creations and as `after` for deletions.
+pub fn diff(before: &Value, after: &Value) -> Value {
+    let empty = Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+    let mut changes = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        let from = before.get(key).unwrap_or(&Value::Null);
+        let to = after.get(key).unwrap_or(&Value::Null);
+        if from != to && !changes.contains_key(key) {
+            changes.insert(key.clone(), json!({ "from": from, "to": to }).0);
+        }
+    }
+    Value::Object(changes)
+}
+
+pub fn search(
+    conn: &DbConnection,
+    filter: &AuditFilter,
+    limit: Option<i32>,
+    offset: Option<i32>,
+) -> DbResult<AuditEventList> {
+    let events_count: i64 = filtered(filter)
+        .count()
+        .get_result(conn)
+        .map_err(Into::<Error>::into)?;
+    filtered(filter)
+        .order(audit_events::id.desc())
+        .limit(limits::coerce_limit(limit).into())
+        .offset(limits::coerce_offset(offset).into())
+        .load::<AuditEventQuery>(conn)
+        .map(|events| AuditEventList {
+            events: events
+                .into_iter()
+                .map(AuditEventQuery::to_audit_event)
+                .collect(),
+            events_count,
+        })
+        .map_err(Into::into)
+}
+
+fn filtered(filter: &AuditFilter) -> audit_events::BoxedQuery<Pg> {
+    let mut query = audit_events::table.into_boxed();
+    if let Some(actor_id) = filter.actor_id {
+        query = query.filter(audit_events::actor_id.eq(actor_id));
+    }
+    if let Some(target_type) = &filter.target_type {
+        query = query.filter(audit_events::target_type.eq(target_type));
+    }
+    if let Some(target_id) = &filter.target_id {
+        query = query.filter(audit_events::target_id.eq(target_id));
+    }
+    if let Some(from) = filter.from {
+        query = query.filter(audit_events::created_at.ge(from));
+    }
+    if let Some(until) = filter.until {
+        query = query.filter(audit_events::created_at.lt(until));
+    }
+    query
+}
@@ -1,5 +1,9 @@
+use crate::cursor::{paginate, Cursor, Page};
 use crate::db;
+use crate::db::audit;
 use crate::db::get_comments::*;
+use crate::db::limits::coerce_limit;
+use crate::db::moderation;
 use crate::db::{DbConnection, DbResult};
 use crate::errors::Error;
 use crate::models::comment::*;
@@ -9,6 +13,8 @@ use ammonia;
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
 use diesel::sql_types::*;
+use serde_json::Value;
+use std::net::IpAddr;
 
 #[derive(QueryableByName, Queryable)]
 struct CommentQ {
@@ -32,27 +38,54 @@ struct CommentQ {
     total_comments: i64,
 }
 
-pub fn for_article(conn: &DbConnection, user: Option<i32>, slug: String) -> DbResult<CommentList> {
-    get_comments(user, slug)
+/// Comments of an article, all of them unless a `limit` or a `cursor` is given.
+pub fn for_article(
+    conn: &DbConnection,
+    user: Option<i32>,
+    slug: String,
+    limit: Option<i32>,
+    cursor: Option<Cursor<i32>>,
+) -> DbResult<CommentList> {
+    let limit = match (limit, &cursor) {
+        (None, None) => None,
+        (limit, _) => Some(coerce_limit(limit)),
+    };
+    get_comments(user, slug, limit, cursor.as_ref())
         .get_results::<CommentQ>(conn)
         .map_err(Into::<Error>::into)
-        .map(|v: Vec<CommentQ>| CommentList {
-            comments_count: (&v).first().map(|x| x.total_comments).unwrap_or(0),
-            comments: v
-                .into_iter()
-                .map(|comment: CommentQ| Comment {
-                    id: comment.comment_id,
-                    body: comment.comment_body,
-                    created_at: format!["{:?}", comment.comment_creation],
-                    updated_at: format!["{:?}", comment.comment_update],
-                    author: Profile {
-                        username: comment.author_username,
-                        bio: comment.author_bio,
-                        image: comment.author_image,
-                        following: comment.is_followed,
-                    },
-                })
-                .collect::<Vec<_>>(),
+        .map(|v: Vec<CommentQ>| {
+            let comments_count = v.first().map(|x| x.total_comments).unwrap_or(0);
+            let page = match limit {
+                Some(limit) => paginate(v, limit, &cursor, cursor.is_none(), |c: &CommentQ| {
+                    (0, c.comment_creation, c.comment_id)
+                }),
+                None => Page {
+                    items: v,
+                    next_cursor: None,
+                    prev_cursor: None,
+                },
+            };
+            CommentList {
+                comments_count,
+                next_cursor: page.next_cursor,
+                prev_cursor: page.prev_cursor,
+                comments: page
+                    .items
+                    .into_iter()
+                    .map(|comment: CommentQ| Comment {
+                        id: comment.comment_id,
+                        body: comment.comment_body,
+                        created_at: format!["{:?}", comment.comment_creation],
+                        updated_at: format!["{:?}", comment.comment_update],
+                        author: Profile {
+                            username: comment.author_username,
+                            bio: comment.author_bio,
+                            image: comment.author_image,
+                            following: comment.is_followed,
+                        },
+                    })
+                    .collect::<Vec<_>>(),
+            }
         })
 }
 
@@ -61,34 +94,95 @@ pub fn create(
     user: i32,
     slug: &String,
     comment: &NewCommentData,
+    ip: Option<IpAddr>,
 ) -> DbResult<Comment> {
     use schema::comments::dsl::*;
+    // Fails for drafts the user isn't allowed to see
+    db::articles::article(conn, Some(user), slug.clone())?;
     let article = find_article_id(conn, slug)?;
     let profile = db::users::find_by_id(conn, user)?.to_profile(false);
 
-    diesel::insert_into(comments)
-        .values((
-            user_id.eq(user),
-            article_id.eq(article),
-            created_at.eq(diesel::dsl::now),
-            updated_at.eq(diesel::dsl::now),
-            body.eq(&ammonia::clean(&comment.body)),
-        ))
-        .get_result::<CommentQuery>(conn)
-        .map(|q| q.to_comment(profile))
-        .map_err(Into::<Error>::into)
+    conn.transaction(|| {
+        let created = diesel::insert_into(comments)
+            .values((
+                user_id.eq(user),
+                article_id.eq(article),
+                created_at.eq(diesel::dsl::now),
+                updated_at.eq(diesel::dsl::now),
+                body.eq(&ammonia::clean(&comment.body)),
+            ))
+            .get_result::<CommentQuery>(conn)
+            .map(|q| q.to_comment(profile))
+            .map_err(Into::<Error>::into)?;
+        audit::record(
+            conn,
+            user,
+            ip,
+            "create_comment",
+            "comment",
+            &created.id.to_string(),
+            audit::diff(&Value::Null, &snapshot(&created, article)),
+        )?;
+        Ok(created)
+    })
 }
 
-pub fn delete(conn: &DbConnection, user: i32, _: &String, comment_id: i32) -> DbResult<Comment> {
+/// Deletes a comment written by the user, or any comment when `moderate` is set.
+pub fn delete(
+    conn: &DbConnection,
+    user: i32,
+    _: &String,
+    comment_id: i32,
+    moderate: bool,
+    ip: Option<IpAddr>,
+) -> DbResult<Comment> {
     use schema::comments::dsl::*;
 
-    let qcomment: CommentQuery = diesel::delete(comments)
-        .filter(id.eq(comment_id).and(user_id.eq(user)))
-        .get_result(conn)
+    conn.transaction(|| {
+        let qcomment: CommentQuery = if moderate {
+            diesel::delete(comments)
+                .filter(id.eq(comment_id))
+                .get_result(conn)
+        } else {
+            diesel::delete(comments)
+                .filter(id.eq(comment_id).and(user_id.eq(user)))
+                .get_result(conn)
+        }
         .map_err(Into::<Error>::into)?;
 
-    let u = db::users::find_by_id(conn, user)?;
-    Ok(qcomment.to_comment(u.to_profile(false)))
+        if qcomment.user_id != user {
+            moderation::record(
+                conn,
+                user,
+                qcomment.user_id,
+                "delete_comment",
+                &comment_id.to_string(),
+            )?;
+        }
+        let u = db::users::find_by_id(conn, qcomment.user_id)?;
+        let commented = qcomment.article_id;
+        let deleted = qcomment.to_comment(u.to_profile(false));
+        audit::record(
+            conn,
+            user,
+            ip,
+            "delete_comment",
+            "comment",
+            &comment_id.to_string(),
+            audit::diff(&snapshot(&deleted, commented), &Value::Null),
+        )?;
+        Ok(deleted)
+    })
+}
+
+// Audited fields of a comment
+fn snapshot(comment: &Comment, commented: i32) -> Value {
+    json!({
+        "articleId": commented,
+        "author": comment.author.username,
+        "body": comment.body,
+    })
+    .0
 }
 
 fn find_article_id(conn: &DbConnection, article_slug: &String) -> DbResult<i32> {
@@ -0,0 +1,60 @@
+use crate::config::Config;
+use crate::db::{DbConnection, DbResult};
+use crate::errors::Error;
+use crate::schema::email_verification_tokens::dsl::*;
+use crate::schema::users;
+use crate::tokens;
+use chrono::Utc;
+use diesel::prelude::*;
+
+/// Creates a token proving ownership of `address` for the given user.
+pub fn create(conn: &DbConnection, user: i32, address: &String, config: &Config) -> DbResult<String> {
+    let token = tokens::generate();
+    diesel::insert_into(email_verification_tokens)
+        .values((
+            user_id.eq(user),
+            email.eq(address),
+            token_hash.eq(tokens::hash(&token)),
+            created_at.eq(diesel::dsl::now),
+            expires_at.eq(Utc::now().naive_utc() + config.email_verification_lifetime),
+        ))
+        .execute(conn)
+        .map_err(Into::<Error>::into)?;
+    Ok(token)
+}
+
+/// Marks the user as verified, provided the token was issued for their current address.
+pub fn confirm(conn: &DbConnection, token: &String) -> DbResult<()> {
+    conn.transaction(|| {
+        let (owner, address): (i32, String) = diesel::update(
+            email_verification_tokens.filter(
+                token_hash
+                    .eq(tokens::hash(token))
+                    .and(used_at.is_null())
+                    .and(expires_at.gt(diesel::dsl::now)),
+            ),
+        )
+        .set(used_at.eq(diesel::dsl::now))
+        .returning((user_id, email))
+        .get_result(conn)
+        .optional()
+        .map_err(Into::<Error>::into)?
+        .ok_or(Error::ValidationFailed(
+            json![{"token": "is invalid or expired"}],
+        ))?;
+
+        let verified = diesel::update(
+            users::table.filter(users::id.eq(owner).and(users::email.eq(&address))),
+        )
+        .set(users::verified_at.eq(diesel::dsl::now))
+        .execute(conn)
+        .map_err(Into::<Error>::into)?;
+        if verified == 1 {
+            Ok(())
+        } else {
+            Err(Error::ValidationFailed(
+                json![{"token": "was issued for another email address"}],
+            ))
+        }
+    })
+}
@@ -1,60 +1,99 @@
-use super::article_query::*;
 use super::limits::*;
+use crate::cursor::Cursor;
+use crate::models::article::{ArticleFilter, ArticleSort};
+use chrono::NaiveDateTime;
 use diesel::pg::*;
 use diesel::query_builder::*;
 use diesel::sql_types::*;
 use diesel::QueryResult;
-use diesel::RunQueryDsl;
 
+/// Rows are loaded by name, see `ListedArticleQuery`, since they don't fit in a tuple.
 #[derive(QueryId)]
 pub struct GetArticles {
     limit: i32,
     offset: i32,
     current_user: Option<i32>,
-    tag: Option<String>,
+    tags: Vec<String>,
+    all_tags: bool,
+    excluded_tags: Vec<String>,
+    authors: Vec<String>,
     favorited: Option<String>,
-    author: Option<String>,
+    status: Option<String>,
+    since: Option<NaiveDateTime>,
+    until: Option<NaiveDateTime>,
+    sort: String,
+    cursor_rank: i64,
+    cursor_time: Option<NaiveDateTime>,
+    cursor_slug: Option<String>,
+    backwards: bool,
 }
 
 impl QueryFragment<Pg> for GetArticles {
     fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        // One more row tells whether there is a page after this one
+        let fetched = self.limit + 1;
         out.push_sql("SELECT * FROM get_articles(");
-        out.push_bind_param::<Integer, _>(&self.limit)?;
+        out.push_bind_param::<Integer, _>(&fetched)?;
         out.push_sql(", ");
         out.push_bind_param::<Integer, _>(&self.offset)?;
         out.push_sql(", ");
         out.push_bind_param::<Nullable<Integer>, _>(&self.current_user)?;
         out.push_sql(", ");
-        out.push_bind_param::<Nullable<Text>, _>(&self.tag)?;
+        out.push_bind_param::<Array<Text>, _>(&self.tags)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Bool, _>(&self.all_tags)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Array<Text>, _>(&self.excluded_tags)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Array<Text>, _>(&self.authors)?;
         out.push_sql(", ");
         out.push_bind_param::<Nullable<Text>, _>(&self.favorited)?;
         out.push_sql(", ");
-        out.push_bind_param::<Nullable<Text>, _>(&self.author)?;
+        out.push_bind_param::<Nullable<Text>, _>(&self.status)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Nullable<Timestamptz>, _>(&self.since)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Nullable<Timestamptz>, _>(&self.until)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Text, _>(&self.sort)?;
+        out.push_sql(", ");
+        out.push_bind_param::<BigInt, _>(&self.cursor_rank)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Nullable<Timestamptz>, _>(&self.cursor_time)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Nullable<Text>, _>(&self.cursor_slug)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Bool, _>(&self.backwards)?;
         out.push_sql(")");
         Ok(())
     }
 }
 
-impl Query for GetArticles {
-    type SqlType = ArticleQuerySql;
-}
-
-impl RunQueryDsl<PgConnection> for GetArticles {}
-
 pub fn get_articles(
     limit: Option<i32>,
     offset: Option<i32>,
     current_user: Option<i32>,
-    tag: Option<String>,
-    favorited: Option<String>,
-    author: Option<String>,
+    filter: ArticleFilter,
+    status: Option<String>,
+    sort: ArticleSort,
+    cursor: Option<&Cursor<String>>,
 ) -> GetArticles {
     GetArticles {
         limit: coerce_limit(limit),
         offset: coerce_offset(offset),
         current_user,
-        tag,
-        favorited,
-        author,
+        tags: filter.tags,
+        all_tags: filter.all_tags,
+        excluded_tags: filter.excluded_tags,
+        authors: filter.authors,
+        favorited: filter.favorited,
+        status,
+        since: filter.since,
+        until: filter.until,
+        sort: sort.as_str().to_owned(),
+        cursor_rank: cursor.map_or(0, |c| c.rank),
+        cursor_time: cursor.map(|c| c.time),
+        cursor_slug: cursor.map(|c| c.key.clone()),
+        backwards: cursor.map_or(false, |c| c.backwards),
     }
 }
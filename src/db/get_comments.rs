@@ -1,3 +1,5 @@
+use crate::cursor::Cursor;
+use chrono::NaiveDateTime;
 use diesel::pg::*;
 use diesel::query_builder::*;
 use diesel::sql_types::*;
@@ -19,15 +21,44 @@ pub type CommentSql = (
 pub struct GetComments {
     slug: String,
     user: Option<i32>,
+    limit: Option<i32>,
+    cursor_time: Option<NaiveDateTime>,
+    cursor_id: Option<i32>,
+    backwards: bool,
 }
 
 impl QueryFragment<Pg> for GetComments {
     fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
-        out.push_sql("SELECT * FROM get_comments(");
+        // Without a limit all comments are returned, as they used to be.
+        // Otherwise one more row tells whether there is a page after this one.
+        let fetched = self.limit.map(|limit| limit + 1);
+        out.push_sql("SELECT page.* FROM (SELECT * FROM get_comments(");
         out.push_bind_param::<Text, _>(&self.slug)?;
         out.push_sql(", ");
         out.push_bind_param::<Nullable<Integer>, _>(&self.user)?;
-        out.push_sql(")");
+        out.push_sql(") AS comments WHERE ");
+        out.push_bind_param::<Nullable<Timestamptz>, _>(&self.cursor_time)?;
+        out.push_sql(" IS NULL OR (");
+        out.push_bind_param::<Bool, _>(&self.backwards)?;
+        out.push_sql(" AND (comments.comment_creation, comments.comment_id) > (");
+        self.push_position(&mut out)?;
+        out.push_sql(")) OR (NOT ");
+        out.push_bind_param::<Bool, _>(&self.backwards)?;
+        out.push_sql(" AND (comments.comment_creation, comments.comment_id) < (");
+        self.push_position(&mut out)?;
+        out.push_sql(")) ORDER BY CASE WHEN ");
+        out.push_bind_param::<Bool, _>(&self.backwards)?;
+        out.push_sql(" THEN comments.comment_creation END ASC, CASE WHEN ");
+        out.push_bind_param::<Bool, _>(&self.backwards)?;
+        out.push_sql(
+            " THEN comments.comment_id END ASC, 
+            comments.comment_creation DESC, comments.comment_id DESC LIMIT ",
+        );
+        out.push_bind_param::<Nullable<Integer>, _>(&fetched)?;
+        out.push_sql(
+            ") AS page 
+            ORDER BY page.comment_creation DESC, page.comment_id DESC",
+        );
         Ok(())
     }
 }
@@ -38,6 +69,26 @@ impl diesel::query_builder::Query for GetComments {
 
 impl diesel::RunQueryDsl<diesel::pg::PgConnection> for GetComments {}
 
-pub fn get_comments(user: Option<i32>, slug: String) -> GetComments {
-    GetComments { slug, user }
+impl GetComments {
+    fn push_position(&self, out: &mut AstPass<Pg>) -> QueryResult<()> {
+        out.push_bind_param::<Nullable<Timestamptz>, _>(&self.cursor_time)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Nullable<Integer>, _>(&self.cursor_id)
+    }
+}
+
+pub fn get_comments(
+    user: Option<i32>,
+    slug: String,
+    limit: Option<i32>,
+    cursor: Option<&Cursor<i32>>,
+) -> GetComments {
+    GetComments {
+        slug,
+        user,
+        limit,
+        cursor_time: cursor.map(|c| c.time),
+        cursor_id: cursor.map(|c| c.key),
+        backwards: cursor.map_or(false, |c| c.backwards),
+    }
 }
@@ -0,0 +1,85 @@
+use crate::config::LoginThrottling;
+use crate::db::{DbConnection, DbResult};
+use crate::errors::Error;
+use crate::schema::login_throttles::dsl::*;
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use std::cmp::min;
+use std::net::IpAddr;
+
+// Throttles are keyed by the submitted email rather than by user id, so that
+// unknown addresses get locked exactly like existing accounts.
+pub fn account_key(email: &str) -> String {
+    format!("account:{}", email.trim().to_lowercase())
+}
+
+pub fn ip_key(ip: &IpAddr) -> String {
+    format!("ip:{}", ip)
+}
+
+/// Fails with `Error::TooManyAttempts` while any of the keys is locked.
+pub fn check(conn: &DbConnection, keys: &[String]) -> DbResult<()> {
+    let until: Option<NaiveDateTime> = login_throttles
+        .filter(key.eq_any(keys).and(locked_until.gt(diesel::dsl::now)))
+        .select(diesel::dsl::max(locked_until))
+        .get_result(conn)
+        .map_err(Into::<Error>::into)?;
+    match until {
+        Some(until) => Err(Error::TooManyAttempts(
+            (until - Utc::now().naive_utc()).num_seconds() + 1,
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Counts a failure and locks the key once `threshold` failures have been reached.
+/// Failures older than the longest lockout are forgotten.
+pub fn record_failure(
+    conn: &DbConnection,
+    throttle_key: &String,
+    threshold: i32,
+    config: &LoginThrottling,
+) -> DbResult<()> {
+    let now = Utc::now().naive_utc();
+    let previous: Option<(i32, NaiveDateTime)> = login_throttles
+        .filter(key.eq(throttle_key))
+        .select((failures, last_failure_at))
+        .get_result(conn)
+        .optional()
+        .map_err(Into::<Error>::into)?;
+    let count = match previous {
+        Some((count, last)) if now - last < config.max_lockout => count + 1,
+        _ => 1,
+    };
+    let lock = if count >= threshold {
+        let exponent = min(count - threshold, 20) as u32;
+        let lockout = config.base_lockout * 2i32.pow(exponent);
+        Some(now + min(lockout, config.max_lockout))
+    } else {
+        None
+    };
+    diesel::insert_into(login_throttles)
+        .values((
+            key.eq(throttle_key),
+            failures.eq(count),
+            last_failure_at.eq(now),
+            locked_until.eq(lock),
+        ))
+        .on_conflict(key)
+        .do_update()
+        .set((
+            failures.eq(count),
+            last_failure_at.eq(now),
+            locked_until.eq(lock),
+        ))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+pub fn clear(conn: &DbConnection, throttle_key: &String) -> DbResult<()> {
+    diesel::delete(login_throttles.filter(key.eq(throttle_key)))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Into::into)
+}
@@ -1,11 +1,24 @@
+pub mod api_keys;
 mod article_query;
 pub mod articles;
+pub mod audit;
 pub mod comments;
+pub mod email_verifications;
 mod get_articles;
 mod get_comments;
 mod limits;
+mod login_throttles;
+mod moderation;
+pub mod password_resets;
+mod refresh_tokens;
+pub mod revisions;
+pub mod revocations;
+mod search_articles;
+mod search_profiles;
 mod select_article_by_slug;
+pub mod slug_history;
 mod tags;
+pub mod two_factor;
 mod user_feed;
 pub mod users;
 
@@ -69,3 +82,11 @@ impl Connection for DbConnection {
 }
 
 pub type DbResult<T> = Result<T, errors::Error>;
+
+/// Escapes the wildcards of `value` for use in a `LIKE` pattern.
+pub fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
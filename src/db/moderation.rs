@@ -0,0 +1,24 @@
+use crate::db::{DbConnection, DbResult};
+use crate::schema::moderation_actions::dsl::*;
+use diesel::prelude::*;
+
+/// Records an action a moderator took on content owned by someone else.
+pub fn record(
+    conn: &DbConnection,
+    moderator: i32,
+    author: i32,
+    performed: &str,
+    on: &str,
+) -> DbResult<()> {
+    diesel::insert_into(moderation_actions)
+        .values((
+            moderator_id.eq(moderator),
+            author_id.eq(author),
+            action.eq(performed),
+            target.eq(on),
+            created_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Into::into)
+}
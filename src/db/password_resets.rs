@@ -0,0 +1,76 @@
+use crate::config::Config;
+use crate::db::users;
+use crate::db::{DbConnection, DbResult};
+use crate::errors::Error;
+use crate::models::user::User;
+use crate::schema::password_reset_tokens::dsl::*;
+use crate::tokens;
+use chrono::Utc;
+use diesel::prelude::*;
+
+/// Creates a reset token for the account registered with `email`.
+/// Returns `None` when no such account exists so that callers can stay silent about it.
+pub fn request(
+    conn: &DbConnection,
+    email: &String,
+    config: &Config,
+) -> DbResult<Option<(User, String)>> {
+    let user = match users::find_by_email(conn, email)? {
+        Some(user) => user,
+        None => return Ok(None),
+    };
+    let token = tokens::generate();
+    diesel::insert_into(password_reset_tokens)
+        .values((
+            user_id.eq(user.id),
+            token_hash.eq(tokens::hash(&token)),
+            created_at.eq(diesel::dsl::now),
+            expires_at.eq(Utc::now().naive_utc() + config.password_reset_lifetime),
+        ))
+        .execute(conn)
+        .map_err(Into::<Error>::into)?;
+    Ok(Some((user, token)))
+}
+
+/// Makes the current password of the account unusable, closes its sessions and
+/// creates a reset token so that the owner has to choose a new password.
+pub fn force(conn: &DbConnection, username: &String, config: &Config) -> DbResult<(User, String)> {
+    conn.transaction(|| {
+        let user = users::find_by_username(conn, username)?;
+        users::set_password(conn, user.id, &tokens::generate(), config)?;
+        request(conn, &user.email, config)?
+            .ok_or(Error::DatabaseError(diesel::result::Error::NotFound))
+    })
+}
+
+pub fn confirm(
+    conn: &DbConnection,
+    token: &String,
+    password: &String,
+    config: &Config,
+) -> DbResult<()> {
+    conn.transaction(|| {
+        let owner: i32 = diesel::update(
+            password_reset_tokens.filter(
+                token_hash
+                    .eq(tokens::hash(token))
+                    .and(used_at.is_null())
+                    .and(expires_at.gt(diesel::dsl::now)),
+            ),
+        )
+        .set(used_at.eq(diesel::dsl::now))
+        .returning(user_id)
+        .get_result(conn)
+        .optional()
+        .map_err(Into::<Error>::into)?
+        .ok_or(Error::ValidationFailed(
+            json![{"token": "is invalid or expired"}],
+        ))?;
+
+        diesel::update(password_reset_tokens.filter(user_id.eq(owner).and(used_at.is_null())))
+            .set(used_at.eq(diesel::dsl::now))
+            .execute(conn)
+            .map_err(Into::<Error>::into)?;
+        users::set_password(conn, owner, password, config)
+    })
+}
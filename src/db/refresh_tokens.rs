@@ -0,0 +1,47 @@
+use crate::config::Config;
+use crate::db::{DbConnection, DbResult};
+use crate::errors::Error;
+use crate::schema::refresh_tokens::dsl::*;
+use crate::tokens;
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+pub fn issue(conn: &DbConnection, user: i32, config: &Config) -> DbResult<String> {
+    diesel::delete(refresh_tokens.filter(user_id.eq(user).and(expires_at.lt(diesel::dsl::now))))
+        .execute(conn)
+        .map_err(Into::<Error>::into)?;
+
+    let token = tokens::generate();
+    diesel::insert_into(refresh_tokens)
+        .values((
+            user_id.eq(user),
+            token_hash.eq(tokens::hash(&token)),
+            created_at.eq(diesel::dsl::now),
+            expires_at.eq(Utc::now().naive_utc() + config.refresh_token_lifetime),
+        ))
+        .execute(conn)
+        .map_err(Into::<Error>::into)?;
+    Ok(token)
+}
+
+/// Deletes the given refresh token and returns the id of its owner.
+/// A token can only be consumed once; expired or unknown tokens are rejected.
+pub fn consume(conn: &DbConnection, token: &str) -> DbResult<i32> {
+    let consumed: Option<(i32, NaiveDateTime)> =
+        diesel::delete(refresh_tokens.filter(token_hash.eq(tokens::hash(token))))
+            .returning((user_id, expires_at))
+            .get_result(conn)
+            .optional()
+            .map_err(Into::<Error>::into)?;
+    match consumed {
+        Some((owner, expiration)) if expiration > Utc::now().naive_utc() => Ok(owner),
+        _ => Err(Error::Unauthorized),
+    }
+}
+
+pub fn discard(conn: &DbConnection, user: i32, token: &str) -> DbResult<()> {
+    diesel::delete(refresh_tokens.filter(user_id.eq(user).and(token_hash.eq(tokens::hash(token)))))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Into::into)
+}
@@ -0,0 +1,218 @@
+use crate::config::SlugOptions;
+use crate::db::articles;
+use crate::db::articles::ArticleEdit;
+use crate::db::limits::{coerce_limit, coerce_offset};
+use crate::db::{DbConnection, DbResult};
+use crate::errors::Error;
+use crate::models::article::{Article, UpdateArticleData};
+use crate::models::revision::{DiffLine, Revision, RevisionDiff, RevisionList, RevisionQuery};
+use crate::schema::{article_revisions, article_tag_associations, tags, users};
+use crate::schema::articles as articles_table;
+use diesel::prelude::*;
+use std::net::IpAddr;
+
+/// Snapshots the current content of the article as its next revision.
+/// Must run in the transaction that changed the article.
+pub fn record(conn: &DbConnection, article_id: i32, editor_id: i32) -> DbResult<()> {
+    // Locking the article serializes concurrent edits, which would otherwise race for the number
+    let (title, description, body): (String, String, String) = articles_table::table
+        .filter(articles_table::id.eq(article_id))
+        .select((
+            articles_table::title,
+            articles_table::description,
+            articles_table::body,
+        ))
+        .for_update()
+        .get_result(conn)
+        .map_err(Into::<Error>::into)?;
+    let tag_list: Vec<String> = article_tag_associations::table
+        .inner_join(tags::table)
+        .filter(article_tag_associations::article_id.eq(article_id))
+        .select(tags::tag)
+        .order(tags::tag)
+        .load(conn)
+        .map_err(Into::<Error>::into)?;
+    let last: Option<i32> = article_revisions::table
+        .filter(article_revisions::article_id.eq(article_id))
+        .select(diesel::dsl::max(article_revisions::revision))
+        .get_result(conn)
+        .map_err(Into::<Error>::into)?;
+    diesel::insert_into(article_revisions::table)
+        .values((
+            article_revisions::article_id.eq(article_id),
+            article_revisions::revision.eq(last.unwrap_or(0) + 1),
+            article_revisions::title.eq(title),
+            article_revisions::description.eq(description),
+            article_revisions::body.eq(body),
+            article_revisions::tag_list.eq(tag_list),
+            article_revisions::editor_id.eq(editor_id),
+            article_revisions::created_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+/// Revisions of the article, the most recent first.
+pub fn list(
+    conn: &DbConnection,
+    viewer: Option<i32>,
+    slug: String,
+    limit: Option<i32>,
+    offset: Option<i32>,
+) -> DbResult<RevisionList> {
+    let article_id = visible_article_id(conn, viewer, slug)?;
+    let revisions_count: i64 = article_revisions::table
+        .filter(article_revisions::article_id.eq(article_id))
+        .count()
+        .get_result(conn)
+        .map_err(Into::<Error>::into)?;
+    article_revisions::table
+        .left_join(users::table)
+        .filter(article_revisions::article_id.eq(article_id))
+        .order(article_revisions::revision.desc())
+        .select(columns())
+        .limit(coerce_limit(limit) as i64)
+        .offset(coerce_offset(offset) as i64)
+        .load::<RevisionQuery>(conn)
+        .map(|revisions| RevisionList {
+            revisions: revisions
+                .into_iter()
+                .map(RevisionQuery::to_revision)
+                .collect(),
+            revisions_count,
+        })
+        .map_err(Into::into)
+}
+
+pub fn find(
+    conn: &DbConnection,
+    viewer: Option<i32>,
+    slug: String,
+    revision: i32,
+) -> DbResult<Revision> {
+    let article_id = visible_article_id(conn, viewer, slug)?;
+    find_by_article(conn, article_id, revision)
+}
+
+pub fn diff(
+    conn: &DbConnection,
+    viewer: Option<i32>,
+    slug: String,
+    from: i32,
+    to: i32,
+) -> DbResult<RevisionDiff> {
+    let article_id = visible_article_id(conn, viewer, slug)?;
+    let old = find_by_article(conn, article_id, from)?;
+    let new = find_by_article(conn, article_id, to)?;
+    Ok(RevisionDiff {
+        from,
+        to,
+        title: diff_lines(&old.title, &new.title),
+        description: diff_lines(&old.description, &new.description),
+        body: diff_lines(&old.body, &new.body),
+    })
+}
+
+/// Brings back the content and tags of an earlier revision, which is saved like any
+/// other edit and recorded as a new revision. The status is left as is, publication
+/// being up to `articles::publish`. Only the author of the article can restore it.
+pub fn restore(
+    conn: &DbConnection,
+    user_id: i32,
+    slug: String,
+    revision: i32,
+    if_match: &Option<String>,
+    slugs: &SlugOptions,
+    ip: Option<IpAddr>,
+) -> DbResult<Article> {
+    let (article_id, author_id): (i32, i32) = articles_table::table
+        .filter(articles_table::slug.eq(&slug))
+        .select((articles_table::id, articles_table::author))
+        .get_result(conn)
+        .map_err(Into::<Error>::into)?;
+    if author_id != user_id {
+        return Err(Error::Forbidden);
+    }
+    let restored = find_by_article(conn, article_id, revision)?;
+    let data = UpdateArticleData {
+        title: Some(restored.title),
+        description: Some(restored.description),
+        body: Some(restored.body),
+        tag_list: Some(restored.tag_list),
+        status: None,
+        publish_at: None,
+    };
+    let edit = ArticleEdit {
+        editor: user_id,
+        slug,
+        data: &data,
+        moderate: false,
+        if_match,
+    };
+    articles::apply_edit(conn, edit, slugs, ip, "restore_article")
+}
+
+// Revisions are only visible to those who can see the article itself
+fn visible_article_id(conn: &DbConnection, viewer: Option<i32>, slug: String) -> DbResult<i32> {
+    articles::article(conn, viewer, slug.clone())?;
+    articles_table::table
+        .filter(articles_table::slug.eq(slug))
+        .select(articles_table::id)
+        .get_result(conn)
+        .map_err(Into::into)
+}
+
+fn find_by_article(conn: &DbConnection, article_id: i32, revision: i32) -> DbResult<Revision> {
+    article_revisions::table
+        .left_join(users::table)
+        .filter(
+            article_revisions::article_id
+                .eq(article_id)
+                .and(article_revisions::revision.eq(revision)),
+        )
+        .select(columns())
+        .get_result::<RevisionQuery>(conn)
+        .map(RevisionQuery::to_revision)
+        .map_err(Into::into)
+}
+
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    ::diff::lines(old, new)
+        .into_iter()
+        .map(|change| match change {
+            ::diff::Result::Left(line) => DiffLine {
+                kind: "removed",
+                line: line.to_owned(),
+            },
+            ::diff::Result::Both(line, _) => DiffLine {
+                kind: "unchanged",
+                line: line.to_owned(),
+            },
+            ::diff::Result::Right(line) => DiffLine {
+                kind: "added",
+                line: line.to_owned(),
+            },
+        })
+        .collect()
+}
+
+fn columns() -> (
+    article_revisions::revision,
+    article_revisions::title,
+    article_revisions::description,
+    article_revisions::body,
+    article_revisions::tag_list,
+    diesel::dsl::Nullable<users::username>,
+    article_revisions::created_at,
+) {
+    (
+        article_revisions::revision,
+        article_revisions::title,
+        article_revisions::description,
+        article_revisions::body,
+        article_revisions::tag_list,
+        users::username.nullable(),
+        article_revisions::created_at,
+    )
+}
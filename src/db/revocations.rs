@@ -0,0 +1,66 @@
+use crate::authentication::AuthData;
+use crate::db::{DbConnection, DbResult};
+use crate::errors::Error;
+use crate::schema::{api_keys, refresh_tokens, revoked_tokens, users};
+use chrono::DateTime;
+use diesel::prelude::*;
+
+/// Checks that the token hasn't been logged out, that its owner didn't
+/// invalidate every session since it was issued and isn't suspended.
+pub fn is_active(conn: &DbConnection, auth: &AuthData) -> bool {
+    let current_version: QueryResult<i32> = users::table
+        .filter(users::id.eq(auth.id).and(users::suspended_at.is_null()))
+        .select(users::token_version)
+        .get_result(conn);
+    let revoked: QueryResult<i64> = revoked_tokens::table
+        .filter(revoked_tokens::jti.eq(&auth.jti))
+        .count()
+        .get_result(conn);
+    match (current_version, revoked) {
+        (Ok(version), Ok(count)) => version == auth.ver && count == 0,
+        _ => false,
+    }
+}
+
+pub fn revoke(conn: &DbConnection, auth: &AuthData) -> DbResult<()> {
+    let expires_at = DateTime::from_timestamp(auth.exp, 0)
+        .map(|date| date.naive_utc())
+        .ok_or(Error::AuthError)?;
+    diesel::delete(revoked_tokens::table.filter(revoked_tokens::expires_at.lt(diesel::dsl::now)))
+        .execute(conn)
+        .map_err(Into::<Error>::into)?;
+    diesel::insert_into(revoked_tokens::table)
+        .values((
+            revoked_tokens::jti.eq(&auth.jti),
+            revoked_tokens::expires_at.eq(expires_at),
+        ))
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+/// Invalidates every access and refresh token currently held by the user, along
+/// with their API keys.
+pub fn revoke_all(conn: &DbConnection, user_id: i32) -> DbResult<()> {
+    conn.transaction(|| {
+        diesel::update(users::table.filter(users::id.eq(user_id)))
+            .set(users::token_version.eq(users::token_version + 1))
+            .execute(conn)
+            .map_err(Into::<Error>::into)?;
+        diesel::delete(refresh_tokens::table.filter(refresh_tokens::user_id.eq(user_id)))
+            .execute(conn)
+            .map_err(Into::<Error>::into)?;
+        diesel::update(
+            api_keys::table.filter(
+                api_keys::user_id
+                    .eq(user_id)
+                    .and(api_keys::revoked_at.is_null()),
+            ),
+        )
+        .set(api_keys::revoked_at.eq(diesel::dsl::now))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Into::into)
+    })
+}
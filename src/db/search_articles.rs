@@ -0,0 +1,43 @@
+use super::limits::*;
+use diesel::pg::*;
+use diesel::query_builder::*;
+use diesel::sql_types::*;
+use diesel::QueryResult;
+
+/// Rows are loaded by name, see `SearchResultQuery`, since they don't fit in a tuple.
+#[derive(QueryId)]
+pub struct SearchArticles {
+    search: String,
+    limit: i32,
+    offset: i32,
+    current_user: Option<i32>,
+}
+
+pub fn search_articles(
+    search: String,
+    limit: Option<i32>,
+    offset: Option<i32>,
+    current_user: Option<i32>,
+) -> SearchArticles {
+    SearchArticles {
+        search,
+        limit: coerce_limit(limit),
+        offset: coerce_offset(offset),
+        current_user,
+    }
+}
+
+impl QueryFragment<Pg> for SearchArticles {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("SELECT * FROM search_articles(");
+        out.push_bind_param::<Text, _>(&self.search)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Integer, _>(&self.limit)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Integer, _>(&self.offset)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Nullable<Integer>, _>(&self.current_user)?;
+        out.push_sql(")");
+        Ok(())
+    }
+}
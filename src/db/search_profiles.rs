@@ -0,0 +1,71 @@
+use super::escape_like;
+use super::limits::*;
+use diesel::pg::*;
+use diesel::query_builder::*;
+use diesel::sql_types::*;
+use diesel::{QueryResult, RunQueryDsl};
+
+/// Active users whose name starts with `search` first, then those whose name is
+/// close to it, the most followed first.
+#[derive(QueryId)]
+pub struct ProfileSearch {
+    prefix: String,
+    search: String,
+    limit: i32,
+    current_user: Option<i32>,
+}
+
+#[derive(Queryable)]
+pub struct ProfileMatch {
+    pub username: String,
+    pub bio: Option<String>,
+    pub image: Option<String>,
+    pub following: bool,
+}
+
+pub fn search_profiles(
+    search: String,
+    limit: Option<i32>,
+    current_user: Option<i32>,
+) -> ProfileSearch {
+    ProfileSearch {
+        prefix: format!("{}%", escape_like(&search)),
+        search,
+        limit: coerce_limit(limit),
+        current_user,
+    }
+}
+
+impl Query for ProfileSearch {
+    type SqlType = (Text, Nullable<Text>, Nullable<Text>, Bool);
+}
+
+impl RunQueryDsl<PgConnection> for ProfileSearch {}
+
+impl QueryFragment<Pg> for ProfileSearch {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql(
+            "SELECT users.username, users.bio, users.image, 
+            EXISTS (SELECT 1 FROM followings 
+                WHERE followed_id = users.id AND follower_id = ",
+        );
+        out.push_bind_param::<Nullable<Integer>, _>(&self.current_user)?;
+        out.push_sql(
+            ") AS following 
+            FROM users 
+            WHERE users.suspended_at IS NULL AND (users.username ILIKE ",
+        );
+        out.push_bind_param::<Text, _>(&self.prefix)?;
+        out.push_sql(" OR users.username % ");
+        out.push_bind_param::<Text, _>(&self.search)?;
+        out.push_sql(") ORDER BY users.username ILIKE ");
+        out.push_bind_param::<Text, _>(&self.prefix)?;
+        out.push_sql(" DESC, similarity(users.username, ");
+        out.push_bind_param::<Text, _>(&self.search)?;
+        out.push_sql(
+            ") DESC, (SELECT COUNT(*) FROM followings WHERE followed_id = users.id) DESC, 
+            users.username LIMIT ",
+        );
+        out.push_bind_param::<Integer, _>(&self.limit)
+    }
+}
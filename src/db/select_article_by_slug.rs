@@ -18,9 +18,9 @@ impl<'a> QueryFragment<diesel::pg::Pg> for SelectArticleBySlug {
     fn walk_ast(&self, mut out: AstPass<diesel::pg::Pg>) -> QueryResult<()> {
         out.push_sql("SELECT * FROM select_articles(");
         out.push_bind_param::<Nullable<Integer>, _>(&self.current_user)?;
-        out.push_sql(", NULL, NULL) as results WHERE results.article_slug = ");
+        out.push_sql(", NULL, NULL, ");
         out.push_bind_param::<Text, _>(&self.slug)?;
-        out.push_sql(" LIMIT 1");
+        out.push_sql(", NULL) LIMIT 1");
         Ok(())
     }
 }
@@ -0,0 +1,50 @@
+use crate::db::{DbConnection, DbResult};
+use crate::schema::{article_slug_history, articles};
+use diesel::prelude::*;
+
+/// Keeps the slug an article is leaving, so that links to it can still be followed.
+/// Must run in the transaction that changed the slug.
+pub fn record(
+    conn: &DbConnection,
+    article_id: i32,
+    old_slug: &str,
+    new_slug: &str,
+) -> DbResult<()> {
+    // A slug coming back into use is no longer history
+    diesel::delete(article_slug_history::table.filter(article_slug_history::slug.eq(new_slug)))
+        .execute(conn)?;
+    diesel::insert_into(article_slug_history::table)
+        .values((
+            article_slug_history::slug.eq(old_slug),
+            article_slug_history::article_id.eq(article_id),
+            article_slug_history::created_at.eq(diesel::dsl::now),
+        ))
+        .on_conflict(article_slug_history::slug)
+        .do_update()
+        .set((
+            article_slug_history::article_id.eq(article_id),
+            article_slug_history::created_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+/// Current slug of the article known as `slug`, which is either its current or a former one.
+/// Current slugs always win over former ones.
+pub fn canonical(conn: &DbConnection, slug: &str) -> DbResult<String> {
+    let current = articles::table
+        .filter(articles::slug.eq(slug))
+        .select(articles::slug)
+        .get_result::<String>(conn)
+        .optional()?;
+    match current {
+        Some(current) => Ok(current),
+        None => article_slug_history::table
+            .inner_join(articles::table)
+            .filter(article_slug_history::slug.eq(slug))
+            .select(articles::slug)
+            .get_result(conn)
+            .map_err(Into::into),
+    }
+}
@@ -1,9 +1,13 @@
+use super::escape_like;
 use super::limits::*;
 use diesel::pg::*;
 use diesel::query_builder::*;
 use diesel::sql_types::*;
 use diesel::{QueryResult, RunQueryDsl};
 
+// Tags are only counted on articles anyone can list, so drafts don't leak them
+const LISTED: &str = "articles.status = 'published' AND articles.published_at <= now()";
+
 #[derive(QueryId)]
 pub struct TagRequest {
     limit: i32,
@@ -33,8 +37,53 @@ impl QueryFragment<Pg> for TagRequest {
             "SELECT tags.tag, COUNT(article_tag_associations.article_id) 
             AS art_count FROM tags 
             INNER JOIN article_tag_associations ON tag_id = tags.id 
-            GROUP BY tags.tag ORDER BY art_count DESC LIMIT ",
+            INNER JOIN articles ON articles.id = article_tag_associations.article_id 
+            WHERE ",
+        );
+        out.push_sql(LISTED);
+        out.push_sql(" GROUP BY tags.tag ORDER BY art_count DESC LIMIT ");
+        out.push_bind_param::<Integer, _>(&self.limit)
+    }
+}
+
+/// Tags starting with `search` first, then those close to it, the most used first.
+#[derive(QueryId)]
+pub struct TagSearch {
+    prefix: String,
+    search: String,
+    limit: i32,
+}
+
+pub fn search_tags(search: String, limit: Option<i32>) -> TagSearch {
+    TagSearch {
+        prefix: format!("{}%", escape_like(&search)),
+        search,
+        limit: coerce_limit(limit),
+    }
+}
+
+impl Query for TagSearch {
+    type SqlType = (Text, BigInt);
+}
+
+impl RunQueryDsl<PgConnection> for TagSearch {}
+
+impl QueryFragment<Pg> for TagSearch {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql(
+            "SELECT tags.tag, COUNT(article_tag_associations.article_id) 
+            AS art_count FROM tags 
+            INNER JOIN article_tag_associations ON tag_id = tags.id 
+            WHERE tags.tag LIKE ",
         );
+        out.push_bind_param::<Text, _>(&self.prefix)?;
+        out.push_sql(" OR tags.tag % ");
+        out.push_bind_param::<Text, _>(&self.search)?;
+        out.push_sql(" GROUP BY tags.tag ORDER BY tags.tag LIKE ");
+        out.push_bind_param::<Text, _>(&self.prefix)?;
+        out.push_sql(" DESC, similarity(tags.tag, ");
+        out.push_bind_param::<Text, _>(&self.search)?;
+        out.push_sql(") DESC, art_count DESC LIMIT ");
         out.push_bind_param::<Integer, _>(&self.limit)
     }
 }
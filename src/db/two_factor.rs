@@ -0,0 +1,234 @@
+use crate::config::LoginThrottling;
+use crate::db::{login_throttles, users, DbConnection, DbResult};
+use crate::errors::Error;
+use crate::schema::{login_challenges, recovery_codes, totp_credentials};
+use crate::tokens;
+use crate::totp;
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+const CHALLENGE_LIFETIME_MINUTES: i64 = 5;
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+
+pub fn is_enabled(conn: &DbConnection, user_id: i32) -> DbResult<bool> {
+    totp_credentials::table
+        .filter(
+            totp_credentials::user_id
+                .eq(user_id)
+                .and(totp_credentials::confirmed_at.is_not_null()),
+        )
+        .count()
+        .get_result(conn)
+        .map(|count: i64| count > 0)
+        .map_err(Into::into)
+}
+
+/// Stores a fresh secret for the user. It only becomes effective once confirmed with a valid code.
+pub fn begin_enrollment(conn: &DbConnection, user_id: i32) -> DbResult<String> {
+    if is_enabled(conn, user_id)? {
+        return Err(Error::ValidationFailed(
+            json![{"two factor authentication": "is already enabled"}],
+        ));
+    }
+    let secret = totp::generate_secret();
+    diesel::insert_into(totp_credentials::table)
+        .values((
+            totp_credentials::user_id.eq(user_id),
+            totp_credentials::secret.eq(&secret),
+            totp_credentials::created_at.eq(diesel::dsl::now),
+        ))
+        .on_conflict(totp_credentials::user_id)
+        .do_update()
+        .set((
+            totp_credentials::secret.eq(&secret),
+            totp_credentials::created_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)
+        .map_err(Into::<Error>::into)?;
+    Ok(secret)
+}
+
+/// Enables two factor authentication and returns a new set of recovery codes.
+pub fn confirm_enrollment(conn: &DbConnection, user_id: i32, code: &String) -> DbResult<Vec<String>> {
+    conn.transaction(|| {
+        let (secret, confirmed_at): (String, Option<NaiveDateTime>) = totp_credentials::table
+            .filter(totp_credentials::user_id.eq(user_id))
+            .select((totp_credentials::secret, totp_credentials::confirmed_at))
+            .get_result(conn)
+            .optional()
+            .map_err(Into::<Error>::into)?
+            .ok_or(Error::ValidationFailed(
+                json![{"two factor authentication": "enrollment wasn't started"}],
+            ))?;
+        if confirmed_at.is_some() {
+            return Err(Error::ValidationFailed(
+                json![{"two factor authentication": "is already enabled"}],
+            ));
+        }
+        let step = totp::verify(&secret, code, None).ok_or_else(invalid_code)?;
+        diesel::update(totp_credentials::table.filter(totp_credentials::user_id.eq(user_id)))
+            .set((
+                totp_credentials::confirmed_at.eq(diesel::dsl::now),
+                totp_credentials::last_used_step.eq(step),
+            ))
+            .execute(conn)
+            .map_err(Into::<Error>::into)?;
+        replace_recovery_codes(conn, user_id)
+    })
+}
+
+pub fn disable(conn: &DbConnection, user_id: i32, code: &String) -> DbResult<()> {
+    conn.transaction(|| {
+        if !is_enabled(conn, user_id)? {
+            return Err(Error::ValidationFailed(
+                json![{"two factor authentication": "is not enabled"}],
+            ));
+        }
+        if !check_code(conn, user_id, code)? {
+            return Err(invalid_code());
+        }
+        diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(user_id)))
+            .execute(conn)
+            .map_err(Into::<Error>::into)?;
+        diesel::delete(totp_credentials::table.filter(totp_credentials::user_id.eq(user_id)))
+            .execute(conn)
+            .map(|_| ())
+            .map_err(Into::into)
+    })
+}
+
+/// Opens a short-lived challenge that must be completed with a code before a session is granted.
+pub fn create_challenge(conn: &DbConnection, user_id: i32) -> DbResult<String> {
+    diesel::delete(login_challenges::table.filter(login_challenges::expires_at.lt(diesel::dsl::now)))
+        .execute(conn)
+        .map_err(Into::<Error>::into)?;
+    let token = tokens::generate();
+    diesel::insert_into(login_challenges::table)
+        .values((
+            login_challenges::user_id.eq(user_id),
+            login_challenges::token_hash.eq(tokens::hash(&token)),
+            login_challenges::created_at.eq(diesel::dsl::now),
+            login_challenges::expires_at
+                .eq(Utc::now().naive_utc() + Duration::minutes(CHALLENGE_LIFETIME_MINUTES)),
+        ))
+        .execute(conn)
+        .map_err(Into::<Error>::into)?;
+    Ok(token)
+}
+
+/// Returns the id of the user who opened the challenge if the code is valid.
+/// Challenges are single use and are dropped after too many wrong codes, which
+/// also count towards the lockout of the account like wrong passwords do.
+pub fn complete_challenge(
+    conn: &DbConnection,
+    token: &String,
+    code: &String,
+    throttling: &LoginThrottling,
+) -> DbResult<i32> {
+    // Failures are committed, the error is only returned afterwards
+    let completed = conn.transaction::<_, Error, _>(|| {
+        let (challenge_id, user_id, attempts): (i32, i32, i32) = login_challenges::table
+            .filter(
+                login_challenges::token_hash
+                    .eq(tokens::hash(token))
+                    .and(login_challenges::expires_at.gt(diesel::dsl::now)),
+            )
+            .select((
+                login_challenges::id,
+                login_challenges::user_id,
+                login_challenges::attempts,
+            ))
+            .for_update()
+            .get_result(conn)
+            .optional()
+            .map_err(Into::<Error>::into)?
+            .ok_or(Error::Unauthorized)?;
+        let account_key = login_throttles::account_key(&users::find_by_id(conn, user_id)?.email);
+        login_throttles::check(conn, &[account_key.clone()])?;
+
+        let valid = check_code(conn, user_id, code)?;
+        let challenge = login_challenges::table.filter(login_challenges::id.eq(challenge_id));
+        if valid || attempts + 1 >= MAX_CHALLENGE_ATTEMPTS {
+            diesel::delete(challenge)
+                .execute(conn)
+                .map_err(Into::<Error>::into)?;
+        } else {
+            diesel::update(challenge)
+                .set(login_challenges::attempts.eq(login_challenges::attempts + 1))
+                .execute(conn)
+                .map_err(Into::<Error>::into)?;
+        }
+        if valid {
+            login_throttles::clear(conn, &account_key)?;
+            Ok(Some(user_id))
+        } else {
+            login_throttles::record_failure(
+                conn,
+                &account_key,
+                throttling.account_threshold,
+                throttling,
+            )?;
+            Ok(None)
+        }
+    })?;
+    completed.ok_or_else(invalid_code)
+}
+
+// Accepts either a TOTP code that wasn't used yet or an unused recovery code,
+// which are then burnt. Must run in a transaction.
+fn check_code(conn: &DbConnection, user_id: i32, code: &String) -> DbResult<bool> {
+    let credential = totp_credentials::table.filter(totp_credentials::user_id.eq(user_id));
+    let (secret, last_step): (String, Option<i64>) = credential
+        .select((totp_credentials::secret, totp_credentials::last_used_step))
+        .for_update()
+        .get_result(conn)
+        .map_err(Into::<Error>::into)?;
+    if let Some(step) = totp::verify(&secret, code, last_step) {
+        diesel::update(credential)
+            .set(totp_credentials::last_used_step.eq(step))
+            .execute(conn)
+            .map_err(Into::<Error>::into)?;
+        return Ok(true);
+    }
+    let code_hash = tokens::hash(&totp::normalize_recovery_code(code));
+    diesel::update(
+        recovery_codes::table.filter(
+            recovery_codes::user_id
+                .eq(user_id)
+                .and(recovery_codes::code_hash.eq(code_hash))
+                .and(recovery_codes::used_at.is_null()),
+        ),
+    )
+    .set(recovery_codes::used_at.eq(diesel::dsl::now))
+    .execute(conn)
+    .map(|used| used > 0)
+    .map_err(Into::into)
+}
+
+fn replace_recovery_codes(conn: &DbConnection, user_id: i32) -> DbResult<Vec<String>> {
+    diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(user_id)))
+        .execute(conn)
+        .map_err(Into::<Error>::into)?;
+    let codes = (0..totp::RECOVERY_CODE_COUNT)
+        .map(|_| totp::generate_recovery_code())
+        .collect::<Vec<_>>();
+    diesel::insert_into(recovery_codes::table)
+        .values(
+            codes
+                .iter()
+                .map(|code| {
+                    (
+                        recovery_codes::user_id.eq(user_id),
+                        recovery_codes::code_hash.eq(tokens::hash(&totp::normalize_recovery_code(code))),
+                    )
+                })
+                .collect::<Vec<_>>(),
+        )
+        .execute(conn)
+        .map_err(Into::<Error>::into)?;
+    Ok(codes)
+}
+
+fn invalid_code() -> Error {
+    Error::ValidationFailed(json![{"code": "is invalid"}])
+}
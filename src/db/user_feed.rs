@@ -1,5 +1,7 @@
 use super::article_query::ArticleQuerySql;
 use super::limits::*;
+use crate::cursor::Cursor;
+use chrono::NaiveDateTime;
 use diesel::pg::*;
 use diesel::query_builder::*;
 use diesel::sql_types::*;
@@ -10,13 +12,24 @@ pub struct UserFeed {
     limit: i32,
     offset: i32,
     user_id: i32,
+    cursor_time: Option<NaiveDateTime>,
+    cursor_slug: Option<String>,
+    backwards: bool,
 }
 
-pub fn user_feed_query(limit: Option<i32>, offset: Option<i32>, user_id: i32) -> UserFeed {
+pub fn user_feed_query(
+    limit: Option<i32>,
+    offset: Option<i32>,
+    user_id: i32,
+    cursor: Option<&Cursor<String>>,
+) -> UserFeed {
     UserFeed {
         limit: coerce_limit(limit),
         offset: coerce_offset(offset),
         user_id,
+        cursor_time: cursor.map(|c| c.time),
+        cursor_slug: cursor.map(|c| c.key.clone()),
+        backwards: cursor.map_or(false, |c| c.backwards),
     }
 }
 
@@ -28,12 +41,43 @@ impl RunQueryDsl<PgConnection> for UserFeed {}
 
 impl QueryFragment<Pg> for UserFeed {
     fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
-        out.push_sql("SELECT * FROM user_feed(");
+        // Same ordering and cursors as `get_articles`, feeds only hold published articles
+        let fetched = self.limit + 1;
+        out.push_sql("SELECT page.* FROM (SELECT * FROM user_feed(");
         out.push_bind_param::<Integer, _>(&self.user_id)?;
-        out.push_sql(") LIMIT ");
-        out.push_bind_param::<Integer, _>(&self.limit)?;
+        out.push_sql(") AS feed WHERE ");
+        out.push_bind_param::<Nullable<Timestamptz>, _>(&self.cursor_time)?;
+        out.push_sql(" IS NULL OR (");
+        out.push_bind_param::<Bool, _>(&self.backwards)?;
+        out.push_sql(" AND (feed.article_publication, feed.article_slug) > (");
+        self.push_position(&mut out)?;
+        out.push_sql(")) OR (NOT ");
+        out.push_bind_param::<Bool, _>(&self.backwards)?;
+        out.push_sql(" AND (feed.article_publication, feed.article_slug) < (");
+        self.push_position(&mut out)?;
+        out.push_sql(")) ORDER BY CASE WHEN ");
+        out.push_bind_param::<Bool, _>(&self.backwards)?;
+        out.push_sql(" THEN feed.article_publication END ASC, CASE WHEN ");
+        out.push_bind_param::<Bool, _>(&self.backwards)?;
+        out.push_sql(
+            " THEN feed.article_slug END ASC, 
+            feed.article_publication DESC, feed.article_slug DESC LIMIT ",
+        );
+        out.push_bind_param::<Integer, _>(&fetched)?;
         out.push_sql(" OFFSET ");
         out.push_bind_param::<Integer, _>(&self.offset)?;
+        out.push_sql(
+            ") AS page 
+            ORDER BY page.article_publication DESC, page.article_slug DESC",
+        );
         Ok(())
     }
 }
+
+impl UserFeed {
+    fn push_position(&self, out: &mut AstPass<Pg>) -> QueryResult<()> {
+        out.push_bind_param::<Nullable<Timestamptz>, _>(&self.cursor_time)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Nullable<Text>, _>(&self.cursor_slug)
+    }
+}
@@ -1,13 +1,28 @@
 use crate::authentication::AuthData;
-use crate::db::{DbConnection, DbResult};
+use crate::config::Config;
+use crate::db::audit;
+use crate::db::limits;
+use crate::db::login_throttles;
+use crate::db::refresh_tokens;
+use crate::db::revocations;
+use crate::db::search_profiles::*;
+use crate::db::two_factor;
+use crate::db::{escape_like, DbConnection, DbResult};
 use crate::errors;
-use crate::models::user::{AuthenticatedUser, Profile, User, UserUpdateData};
+use crate::models::user::{
+    AuthenticatedUser, LoginResponse, ManagedUserList, Profile, ProfileList, Role, User,
+    UserUpdateData,
+};
+use crate::password;
 use crate::schema;
 use crate::schema::followings;
 use crate::schema::users;
 use ammonia;
+use chrono::{NaiveDateTime, Utc};
+use diesel::pg::Pg;
 use errors::Error;
-use scrypt;
+use serde_json::Value;
+use std::net::IpAddr;
 
 use diesel::prelude::*;
 
@@ -34,37 +49,114 @@ pub fn create(
     username: &String,
     email: &String,
     password: String,
-    secret: &String,
+    config: &Config,
 ) -> DbResult<AuthenticatedUser> {
-    let hash = make_hash(password)?;
+    let hash = password::hash(&password, &config.password_hashing)?;
 
-    diesel::insert_into(users::table)
-        .values(NewUserData {
-            username: &ammonia::clean(&username),
-            email: &ammonia::clean(&email),
-            hash: &ammonia::clean(&hash),
-        })
-        .get_result(conn)
-        .map_err(Into::into)
-        .and_then(|u: User| u.to_authenticated(secret))
+    conn.transaction(|| {
+        diesel::insert_into(users::table)
+            .values(NewUserData {
+                username: &ammonia::clean(username),
+                email: &ammonia::clean(email),
+                hash: &ammonia::clean(&hash),
+            })
+            .get_result(conn)
+            .map_err(Into::into)
+            .and_then(|u: User| log_in(conn, u, config))
+    })
 }
 
 pub fn authenticate(
     conn: &DbConnection,
     email: &String,
-    password: &String,
-    secret: &String,
+    clear_password: &String,
+    ip: Option<IpAddr>,
+    config: &Config,
+) -> DbResult<LoginResponse> {
+    let throttling = &config.login_throttling;
+    let account_key = login_throttles::account_key(email);
+    let ip_key = ip.as_ref().map(login_throttles::ip_key);
+    let mut keys = vec![account_key.clone()];
+    keys.extend(ip_key.clone());
+    login_throttles::check(conn, &keys)?;
+
+    let user = match find_by_email(conn, email)? {
+        Some(user) if password::verify(clear_password, &user.hash)? => user,
+        maybe_user => {
+            if maybe_user.is_none() {
+                // Spend as long as for an existing account so timing doesn't reveal the difference
+                password::hash(clear_password, &config.password_hashing)?;
+            }
+            login_throttles::record_failure(
+                conn,
+                &account_key,
+                throttling.account_threshold,
+                throttling,
+            )?;
+            if let Some(ip_key) = &ip_key {
+                login_throttles::record_failure(conn, ip_key, throttling.ip_threshold, throttling)?;
+            }
+            return Err(Error::AuthError);
+        }
+    };
+    ensure_not_suspended(&user)?;
+    if password::needs_rehash(&user.hash, &config.password_hashing) {
+        let rehashed = password::hash(clear_password, &config.password_hashing)?;
+        diesel::update(users::table.filter(users::id.eq(user.id)))
+            .set(users::hash.eq(rehashed))
+            .execute(conn)
+            .map_err(Into::<Error>::into)?;
+    }
+    if two_factor::is_enabled(conn, user.id)? {
+        // The account stays throttled until the second factor is passed as well
+        two_factor::create_challenge(conn, user.id).map(LoginResponse::SecondFactorRequired)
+    } else {
+        login_throttles::clear(conn, &account_key)?;
+        log_in(conn, user, config).map(LoginResponse::Authenticated)
+    }
+}
+
+pub fn complete_two_factor(
+    conn: &DbConnection,
+    challenge: &String,
+    code: &String,
+    config: &Config,
 ) -> DbResult<AuthenticatedUser> {
-    schema::users::table
-        .filter(users::email.eq(email))
-        .get_result(conn)
-        .optional()
-        .map_err(Into::into)
-        .and_then(|maybe_user: Option<User>| match maybe_user {
-            Some(user) => scrypt::scrypt_check(password, &user.hash)
-                .map_err(|_| Error::AuthError)
-                .and_then(|_| user.to_authenticated(secret)),
-            None => Err(Error::ValidationFailed(json![{"email": "doesn't exist"}])),
+    let user_id =
+        two_factor::complete_challenge(conn, challenge, code, &config.login_throttling)?;
+    find_by_id(conn, user_id).and_then(|user| log_in(conn, user, config))
+}
+
+pub fn refresh(
+    conn: &DbConnection,
+    refresh_token: &String,
+    config: &Config,
+) -> DbResult<AuthenticatedUser> {
+    conn.transaction(|| {
+        let user_id = refresh_tokens::consume(conn, refresh_token)?;
+        find_by_id(conn, user_id).and_then(|user| log_in(conn, user, config))
+    })
+}
+
+pub fn logout(
+    conn: &DbConnection,
+    auth: &AuthData,
+    refresh_token: Option<&String>,
+) -> DbResult<()> {
+    revocations::revoke(conn, auth)?;
+    match refresh_token {
+        Some(token) => refresh_tokens::discard(conn, auth.id, token),
+        None => Ok(()),
+    }
+}
+
+fn log_in(conn: &DbConnection, user: User, config: &Config) -> DbResult<AuthenticatedUser> {
+    ensure_not_suspended(&user)?;
+    let refresh_token = refresh_tokens::issue(conn, user.id, config)?;
+    user.to_authenticated(config)
+        .map(|authenticated| AuthenticatedUser {
+            refresh_token: Some(refresh_token),
+            ..authenticated
         })
 }
 
@@ -94,6 +186,31 @@ pub fn profile(
     })
 }
 
+/// Profiles matching a partial or misspelled username, for autocompletion.
+pub fn search(
+    conn: &DbConnection,
+    search: String,
+    limit: Option<i32>,
+    current_user: Option<i32>,
+) -> DbResult<ProfileList> {
+    search_profiles(search, limit, current_user)
+        .load(conn)
+        .map_err(Into::into)
+        .map(|found: Vec<ProfileMatch>| {
+            ProfileList(
+                found
+                    .into_iter()
+                    .map(|profile| Profile {
+                        username: profile.username,
+                        bio: profile.bio,
+                        image: profile.image,
+                        following: profile.following,
+                    })
+                    .collect(),
+            )
+        })
+}
+
 pub fn find_by_username(conn: &DbConnection, username: &String) -> DbResult<User> {
     schema::users::table
         .filter(users::username.eq(username))
@@ -101,6 +218,125 @@ pub fn find_by_username(conn: &DbConnection, username: &String) -> DbResult<User
         .map_err(Into::into)
 }
 
+pub fn find_by_email(conn: &DbConnection, email: &String) -> DbResult<Option<User>> {
+    schema::users::table
+        .filter(users::email.eq(email))
+        .get_result(conn)
+        .optional()
+        .map_err(Into::into)
+}
+
+/// Lifts the lockout caused by failed logins on the account.
+pub fn unlock(conn: &DbConnection, username: &String) -> DbResult<()> {
+    let user = find_by_username(conn, username)?;
+    login_throttles::clear(conn, &login_throttles::account_key(&user.email))
+}
+
+fn ensure_not_suspended(user: &User) -> DbResult<()> {
+    match user.suspended_at {
+        Some(_) => Err(Error::AccountSuspended),
+        None => Ok(()),
+    }
+}
+
+/// Accounts matching the filters, for administrators. `search` matches
+/// usernames and email addresses containing it, case-insensitively.
+pub fn list(
+    conn: &DbConnection,
+    search: &Option<String>,
+    role: &Option<Role>,
+    suspended: Option<bool>,
+    limit: Option<i32>,
+    offset: Option<i32>,
+) -> DbResult<ManagedUserList> {
+    let users_count: i64 = filtered(search, role, suspended)
+        .count()
+        .get_result(conn)
+        .map_err(Into::<Error>::into)?;
+    filtered(search, role, suspended)
+        .order(users::id)
+        .limit(limits::coerce_limit(limit).into())
+        .offset(limits::coerce_offset(offset).into())
+        .load::<User>(conn)
+        .map(|found| ManagedUserList {
+            users: found.into_iter().map(User::to_managed).collect(),
+            users_count,
+        })
+        .map_err(Into::into)
+}
+
+fn filtered<'a>(
+    search: &'a Option<String>,
+    role: &'a Option<Role>,
+    suspended: Option<bool>,
+) -> users::BoxedQuery<'a, Pg> {
+    let mut query = users::table.into_boxed();
+    if let Some(search) = search {
+        let pattern = format!("%{}%", escape_like(search));
+        query = query.filter(
+            users::username
+                .ilike(pattern.clone())
+                .or(users::email.ilike(pattern)),
+        );
+    }
+    if let Some(role) = role {
+        query = query.filter(users::role.eq(role.as_str()));
+    }
+    match suspended {
+        Some(true) => query = query.filter(users::suspended_at.is_not_null()),
+        Some(false) => query = query.filter(users::suspended_at.is_null()),
+        None => (),
+    }
+    query
+}
+
+/// Suspending an account closes all of its sessions, and it can't be used
+/// again until it is unsuspended.
+pub fn set_suspended(conn: &DbConnection, username: &String, suspend: bool) -> DbResult<User> {
+    conn.transaction(|| {
+        let suspended_at = if suspend {
+            Some(Utc::now().naive_utc())
+        } else {
+            None
+        };
+        let user: User = diesel::update(users::table.filter(users::username.eq(username)))
+            .set(users::suspended_at.eq(suspended_at))
+            .get_result(conn)
+            .map_err(Into::<Error>::into)?;
+        if suspend {
+            revocations::revoke_all(conn, user.id)?;
+        }
+        Ok(user)
+    })
+}
+
+pub fn set_role(conn: &DbConnection, username: &String, role: Role) -> DbResult<User> {
+    diesel::update(users::table.filter(users::username.eq(username)))
+        .set(users::role.eq(role.as_str()))
+        .get_result(conn)
+        .map_err(Into::into)
+}
+
+/// Deletes the account along with its content. Favorite counts of the
+/// articles it had favorited are adjusted accordingly.
+pub fn delete(conn: &DbConnection, username: &String) -> DbResult<()> {
+    use crate::schema::{articles, favorites};
+    conn.transaction(|| {
+        let user = find_by_username(conn, username)?;
+        let favorited = favorites::table
+            .filter(favorites::user_id.eq(user.id))
+            .select(favorites::article_id);
+        diesel::update(articles::table.filter(articles::id.eq_any(favorited)))
+            .set(articles::favorites_count.eq(articles::favorites_count - 1))
+            .execute(conn)
+            .map_err(Into::<Error>::into)?;
+        diesel::delete(users::table.filter(users::id.eq(user.id)))
+            .execute(conn)
+            .map(|_| ())
+            .map_err(Into::into)
+    })
+}
+
 pub fn find_by_id(conn: &DbConnection, id: i32) -> DbResult<User> {
     schema::users::table
         .filter(users::id.eq(id))
@@ -112,21 +348,86 @@ pub fn update(
     conn: &DbConnection,
     id: i32,
     upd: &UserUpdateData,
-    secret: &String,
+    config: &Config,
+    ip: Option<IpAddr>,
 ) -> DbResult<AuthenticatedUser> {
     let data = UpdateUserData {
         username: upd.username.clone().map(|a| ammonia::clean(&a)),
         email: upd.email.clone().map(|a| ammonia::clean(&a)),
-        hash: upd.password.clone().clone().and_then(|v| make_hash(v).ok()),
+        hash: upd
+            .password
+            .as_ref()
+            .map(|v| password::hash(v, &config.password_hashing))
+            .transpose()?,
         image: upd.image.clone().map(|a| ammonia::clean(&a)),
         bio: upd.bio.clone().map(|a| ammonia::clean(&a)),
     };
 
+    conn.transaction(|| {
+        let before = find_by_id(conn, id)?;
+        let password_changed = data.hash.is_some();
+        let email_changed = match &data.email {
+            Some(new_email) => before.email != *new_email,
+            None => false,
+        };
+        if password_changed {
+            revocations::revoke_all(conn, id)?;
+        }
+        let mut user: User = diesel::update(users::table.filter(users::id.eq(id)))
+            .set(data)
+            .get_result(conn)
+            .map_err(Into::<Error>::into)?;
+        if email_changed {
+            user = diesel::update(users::table.filter(users::id.eq(id)))
+                .set(users::verified_at.eq(None::<NaiveDateTime>))
+                .get_result(conn)
+                .map_err(Into::<Error>::into)?;
+        }
+        let mut changes = audit::diff(&snapshot(&before), &snapshot(&user));
+        if password_changed {
+            // Never log hashes, only the fact that the password changed
+            changes["password"] = json!("changed").0;
+        }
+        audit::record(conn, id, ip, "update_user", "user", &id.to_string(), changes)?;
+        if password_changed {
+            log_in(conn, user, config)
+        } else {
+            user.to_authenticated(config)
+        }
+    })
+}
+
+// Audited fields of an account
+fn snapshot(user: &User) -> Value {
+    json!({
+        "username": user.username,
+        "email": user.email,
+        "bio": user.bio,
+        "image": user.image,
+    })
+    .0
+}
+
+pub fn ensure_verified(conn: &DbConnection, id: i32) -> DbResult<()> {
+    find_by_id(conn, id).and_then(|user| match user.verified_at {
+        Some(_) => Ok(()),
+        None => Err(Error::EmailNotVerified),
+    })
+}
+
+/// Replaces the password and closes every session opened with the previous one.
+pub fn set_password(
+    conn: &DbConnection,
+    id: i32,
+    clear_password: &String,
+    config: &Config,
+) -> DbResult<()> {
+    let hash = password::hash(clear_password, &config.password_hashing)?;
     diesel::update(users::table.filter(users::id.eq(id)))
-        .set(data)
-        .get_result(conn)
-        .map_err(Into::into)
-        .and_then(|u: User| u.to_authenticated(secret))
+        .set(users::hash.eq(hash))
+        .execute(conn)
+        .map_err(Into::<Error>::into)?;
+    revocations::revoke_all(conn, id)
 }
 
 pub fn follow(conn: &DbConnection, username: &String, id: i32) -> DbResult<Profile> {
@@ -165,18 +466,3 @@ pub fn unfollow(conn: &DbConnection, username: &String, id: i32) -> DbResult<Pro
             }
         })
 }
-
-impl From<scrypt::errors::InvalidParams> for Error {
-    fn from(err: scrypt::errors::InvalidParams) -> Error {
-        Error::InternalServerError("password".to_owned(), err.to_string())
-    }
-}
-
-fn make_hash(password: String) -> Result<String, Error> {
-    scrypt::ScryptParams::new(10, 8, 1)
-        .map_err(Into::into)
-        .and_then(|value| {
-            scrypt::scrypt_simple(&password, &value)
-                .map_err(|err| Error::InternalServerError("password".to_owned(), err.to_string()))
-        })
-}
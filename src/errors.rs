@@ -1,3 +1,4 @@
+use crate::authentication::AUTHORIZATION_SCHEMES;
 use diesel::result;
 use rocket::http::Status;
 use rocket::response;
@@ -13,6 +14,11 @@ pub enum Error {
     AuthError,
     Forbidden,
     Unauthorized,
+    EmailNotVerified,
+    AccountSuspended,
+    TooManyAttempts(i64),
+    PreconditionFailed,
+    PreconditionRequired,
 }
 
 impl From<result::Error> for Error {
@@ -23,8 +29,24 @@ impl From<result::Error> for Error {
 
 impl<'r> Responder<'r> for Error {
     fn respond_to(self, req: &Request) -> response::Result<'r> {
+        let extra_header = match &self {
+            Error::TooManyAttempts(seconds) => Some(("Retry-After", seconds.to_string())),
+            Error::Unauthorized => Some((
+                "WWW-Authenticate",
+                AUTHORIZATION_SCHEMES
+                    .iter()
+                    .map(|scheme| format!["{} realm=\"conduit\"", scheme])
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )),
+            _ => None,
+        };
         let (err, code) = dispatch_error(self);
-        status::Custom(code, Json(json![{ "errors": err }])).respond_to(req)
+        let mut response = status::Custom(code, Json(json![{ "errors": err }])).respond_to(req)?;
+        if let Some((name, value)) = extra_header {
+            response.set_raw_header(name, value);
+        }
+        Ok(response)
     }
 }
 
@@ -90,6 +112,36 @@ fn dispatch_error(error: Error) -> (JsonValue, Status) {
             }],
             Status::Forbidden,
         ),
+        Error::EmailNotVerified => (
+            json![{
+                "email": "must be verified before publishing content"
+            }],
+            Status::Forbidden,
+        ),
+        Error::AccountSuspended => (
+            json![{
+                "account": "is suspended"
+            }],
+            Status::Forbidden,
+        ),
+        Error::TooManyAttempts(seconds) => (
+            json![{
+                "login": format!["too many failed attempts, retry in {} seconds", seconds]
+            }],
+            Status::TooManyRequests,
+        ),
+        Error::PreconditionFailed => (
+            json![{
+                "resource": "has been modified since it was retrieved"
+            }],
+            Status::PreconditionFailed,
+        ),
+        Error::PreconditionRequired => (
+            json![{
+                "If-Match": "header is required to modify this resource"
+            }],
+            Status::PreconditionRequired,
+        ),
         Error::ValidationFailed(value) => (value, Status::UnprocessableEntity),
     }
 }
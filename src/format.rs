@@ -1,5 +1,26 @@
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime};
 
 pub fn encode_datetime(dt: NaiveDateTime) -> String {
     format!["{:?}", dt]
 }
+
+/// Reverse of `encode_datetime`.
+pub fn decode_datetime(s: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f").ok()
+}
+
+/// RFC 3339 timestamp, whatever its offset, converted to UTC.
+pub fn decode_rfc3339(s: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(s).ok().map(|parsed| parsed.naive_utc())
+}
+
+/// IMF-fixdate used by HTTP headers such as `Last-Modified`.
+pub fn encode_http_date(dt: NaiveDateTime) -> String {
+    dt.format(HTTP_DATE).to_string()
+}
+
+pub fn decode_http_date(s: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(s.trim(), HTTP_DATE).ok()
+}
+
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
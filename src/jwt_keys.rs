@@ -0,0 +1,311 @@
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use std::env;
+use std::fs;
+use std::str::FromStr;
+
+// DER encoded object identifiers of the supported public key types
+const RSA_OID: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const ED25519_OID: &[u8] = &[0x2b, 0x65, 0x70];
+
+const SEQUENCE: u8 = 0x30;
+const INTEGER: u8 = 0x02;
+const BIT_STRING: u8 = 0x03;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+
+pub struct SigningKey {
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    pub key: EncodingKey,
+}
+
+pub struct VerificationKey {
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    pub key: DecodingKey,
+    pub jwk: Option<Jwk>,
+}
+
+/// Public half of an asymmetric key, as published on the JWKS endpoint.
+#[derive(Serialize, Clone)]
+pub struct Jwk {
+    pub kty: String,
+    pub kid: String,
+    pub alg: String,
+    #[serde(rename = "use")]
+    pub usage: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+}
+
+/// Keys used to sign new tokens and to verify incoming ones.
+///
+/// With `JWT_ALGORITHM=HS256` (the default) tokens are signed with `SECRET_KEY`.
+/// With `RS256` or `EdDSA`, `JWT_PRIVATE_KEY` and `JWT_PUBLIC_KEY` point to the PEM
+/// files of the current key pair, identified by `JWT_KEY_ID`. Retired public keys
+/// that should still be accepted are listed in `JWT_VERIFICATION_KEYS` as
+/// comma separated `kid=path` pairs.
+pub struct JwtKeys {
+    pub signing: SigningKey,
+    pub verification: Vec<VerificationKey>,
+}
+
+impl JwtKeys {
+    pub fn from_env(secret: &String) -> Result<JwtKeys, String> {
+        let algorithm = Algorithm::from_str(
+            &env::var("JWT_ALGORITHM").unwrap_or("HS256".to_owned()),
+        )
+        .map_err(|err| format!("JWT_ALGORITHM parsing failed: {}", err))?;
+        let kid = env::var("JWT_KEY_ID").ok();
+
+        match algorithm {
+            Algorithm::HS256 => Ok(JwtKeys {
+                signing: SigningKey {
+                    kid: kid.clone(),
+                    algorithm,
+                    key: EncodingKey::from_secret(secret.as_ref()),
+                },
+                verification: vec![VerificationKey {
+                    kid,
+                    algorithm,
+                    key: DecodingKey::from_secret(secret.as_ref()),
+                    jwk: None,
+                }],
+            }),
+            Algorithm::RS256 | Algorithm::EdDSA => {
+                let kid = kid.ok_or("JWT_KEY_ID environment variable required".to_owned())?;
+                let private_pem = read_key_file("JWT_PRIVATE_KEY")?;
+                let key = match algorithm {
+                    Algorithm::RS256 => EncodingKey::from_rsa_pem(&private_pem),
+                    _ => EncodingKey::from_ed_pem(&private_pem),
+                }
+                .map_err(|err| format!("JWT_PRIVATE_KEY is invalid: {}", err))?;
+
+                let mut verification = vec![load_public_key(
+                    kid.clone(),
+                    &read_key_file("JWT_PUBLIC_KEY")?,
+                )?];
+                if algorithm != verification[0].algorithm {
+                    return Err("JWT_PUBLIC_KEY doesn't match JWT_ALGORITHM".to_owned());
+                }
+                if let Ok(previous) = env::var("JWT_VERIFICATION_KEYS") {
+                    for entry in previous.split(',').filter(|e| !e.trim().is_empty()) {
+                        let mut parts = entry.trim().splitn(2, '=');
+                        match (parts.next(), parts.next()) {
+                            (Some(previous_kid), Some(path)) => {
+                                let pem = fs::read(path).map_err(|err| {
+                                    format!("Couldn't read verification key {}: {}", path, err)
+                                })?;
+                                verification.push(load_public_key(previous_kid.to_owned(), &pem)?);
+                            }
+                            _ => {
+                                return Err(format!(
+                                    "JWT_VERIFICATION_KEYS entry must be kid=path, got {}",
+                                    entry
+                                ))
+                            }
+                        }
+                    }
+                }
+                Ok(JwtKeys {
+                    signing: SigningKey {
+                        kid: Some(kid),
+                        algorithm,
+                        key,
+                    },
+                    verification,
+                })
+            }
+            other => Err(format!("Unsupported JWT_ALGORITHM: {:?}", other)),
+        }
+    }
+
+    /// Key matching the `kid` of a token header. Tokens without a `kid` were
+    /// signed before rotation was configured and are checked against the current key.
+    pub fn find(&self, kid: &Option<String>) -> Option<&VerificationKey> {
+        match kid {
+            Some(_) => self.verification.iter().find(|key| &key.kid == kid),
+            None => self.verification.first(),
+        }
+    }
+
+    pub fn jwks(&self) -> Vec<Jwk> {
+        self.verification
+            .iter()
+            .filter_map(|key| key.jwk.clone())
+            .collect()
+    }
+}
+
+fn read_key_file(variable: &str) -> Result<Vec<u8>, String> {
+    let path = env::var(variable)
+        .map_err(|err| format!("{} environment variable required: {}", variable, err))?;
+    fs::read(&path).map_err(|err| format!("Couldn't read {} ({}): {}", variable, path, err))
+}
+
+fn load_public_key(kid: String, pem_content: &[u8]) -> Result<VerificationKey, String> {
+    let invalid = |reason: &str| format!("Public key {} is invalid: {}", kid, reason);
+    let parsed = pem::parse(pem_content).map_err(|err| invalid(&err.to_string()))?;
+    let (oid, public_key) =
+        parse_subject_public_key_info(&parsed.contents).ok_or(invalid("malformed DER"))?;
+
+    if oid == RSA_OID {
+        let (n, e) = parse_rsa_public_key(public_key).ok_or(invalid("malformed RSA key"))?;
+        Ok(VerificationKey {
+            algorithm: Algorithm::RS256,
+            key: DecodingKey::from_rsa_raw_components(n, e),
+            jwk: Some(Jwk {
+                kty: "RSA".to_owned(),
+                kid: kid.clone(),
+                alg: "RS256".to_owned(),
+                usage: "sig".to_owned(),
+                n: Some(base64url(n)),
+                e: Some(base64url(e)),
+                crv: None,
+                x: None,
+            }),
+            kid: Some(kid),
+        })
+    } else if oid == ED25519_OID {
+        Ok(VerificationKey {
+            algorithm: Algorithm::EdDSA,
+            key: DecodingKey::from_ed_der(public_key),
+            jwk: Some(Jwk {
+                kty: "OKP".to_owned(),
+                kid: kid.clone(),
+                alg: "EdDSA".to_owned(),
+                usage: "sig".to_owned(),
+                n: None,
+                e: None,
+                crv: Some("Ed25519".to_owned()),
+                x: Some(base64url(public_key)),
+            }),
+            kid: Some(kid),
+        })
+    } else {
+        Err(invalid("only RSA and Ed25519 keys are supported"))
+    }
+}
+
+fn base64url(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+// SubjectPublicKeyInfo ::= SEQUENCE { algorithm SEQUENCE { OID, params }, BIT STRING }
+fn parse_subject_public_key_info(der: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (spki, _) = read_element(der, SEQUENCE)?;
+    let (algorithm, rest) = read_element(spki, SEQUENCE)?;
+    let (oid, _) = read_element(algorithm, OBJECT_IDENTIFIER)?;
+    let (bits, _) = read_element(rest, BIT_STRING)?;
+    // The first byte of a bit string is the count of unused bits, always 0 for keys
+    match bits.split_first() {
+        Some((0, key)) => Some((oid, key)),
+        _ => None,
+    }
+}
+
+// RSAPublicKey ::= SEQUENCE { modulus INTEGER, publicExponent INTEGER }
+fn parse_rsa_public_key(der: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (key, _) = read_element(der, SEQUENCE)?;
+    let (n, rest) = read_element(key, INTEGER)?;
+    let (e, _) = read_element(rest, INTEGER)?;
+    Some((strip_leading_zeros(n), strip_leading_zeros(e)))
+}
+
+fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| *b != 0)
+        .unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+/// Reads one DER element with the expected tag, returning its content and the remaining input.
+fn read_element(input: &[u8], expected_tag: u8) -> Option<(&[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    if tag != expected_tag {
+        return None;
+    }
+    let (&first, rest) = rest.split_first()?;
+    let (length, rest) = if first & 0x80 == 0 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let length = rest[..count]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (length, &rest[count..])
+    };
+    if rest.len() < length {
+        None
+    } else {
+        Some((&rest[..length], &rest[length..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_short_and_long_lengths() {
+        assert_eq!(
+            read_element(&[INTEGER, 2, 1, 2, 3], INTEGER),
+            Some((&[1u8, 2][..], &[3u8][..]))
+        );
+        let mut long = vec![BIT_STRING, 0x82, 0x01, 0x00];
+        long.extend(vec![7; 256]);
+        let (content, rest) = read_element(&long, BIT_STRING).unwrap();
+        assert_eq!(content.len(), 256);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_elements() {
+        assert_eq!(read_element(&[], INTEGER), None);
+        assert_eq!(read_element(&[INTEGER], INTEGER), None);
+        assert_eq!(read_element(&[INTEGER, 3, 1, 2], INTEGER), None);
+        assert_eq!(read_element(&[INTEGER, 0x82, 0x01], INTEGER), None);
+        assert_eq!(read_element(&[INTEGER, 0x81, 0x02, 1], INTEGER), None);
+    }
+
+    #[test]
+    fn rejects_overlong_length_encodings() {
+        assert_eq!(read_element(&[INTEGER, 0x80, 1], INTEGER), None);
+        assert_eq!(read_element(&[INTEGER, 0x85, 0, 0, 0, 0, 1, 1], INTEGER), None);
+    }
+
+    #[test]
+    fn rejects_unexpected_tags() {
+        assert_eq!(read_element(&[INTEGER, 1, 1], SEQUENCE), None);
+    }
+
+    #[test]
+    fn parses_ed25519_subject_public_key_info() {
+        let mut der = vec![SEQUENCE, 0x2a, SEQUENCE, 0x05, OBJECT_IDENTIFIER, 0x03];
+        der.extend(ED25519_OID);
+        der.extend(&[BIT_STRING, 0x21, 0x00]);
+        der.extend(&[9; 32]);
+        let (oid, key) = parse_subject_public_key_info(&der).unwrap();
+        assert_eq!(oid, ED25519_OID);
+        assert_eq!(key, &[9; 32][..]);
+    }
+
+    #[test]
+    fn parses_rsa_public_keys() {
+        let der = [
+            SEQUENCE, 0x0a, INTEGER, 0x03, 0x00, 0xc3, 0x5f, INTEGER, 0x03, 0x01, 0x00, 0x01,
+        ];
+        let (n, e) = parse_rsa_public_key(&der).unwrap();
+        assert_eq!(n, &[0xc3, 0x5f][..]);
+        assert_eq!(e, &[0x01, 0x00, 0x01][..]);
+    }
+}
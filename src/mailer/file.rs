@@ -0,0 +1,45 @@
+use super::{Mail, Mailer};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Appends every mail to a file, or prints it on stdout when no path is given.
+/// Meant for local development where no SMTP server is available.
+pub struct FileMailer {
+    from: String,
+    path: Option<PathBuf>,
+    lock: Mutex<()>,
+}
+
+impl FileMailer {
+    pub fn new(from: String, path: Option<PathBuf>) -> FileMailer {
+        FileMailer {
+            from,
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, mail: Mail) -> Result<(), String> {
+        let content = format!(
+            "From: {}\nTo: {}\nSubject: {}\n\n{}\n\n",
+            self.from, mail.to, mail.subject, mail.body
+        );
+        let _guard = self.lock.lock().map_err(|err| err.to_string())?;
+        match &self.path {
+            Some(path) => OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(content.as_bytes()))
+                .map_err(|err| err.to_string()),
+            None => {
+                print!("{}", content);
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+mod file;
+mod smtp;
+
+pub use file::FileMailer;
+pub use smtp::SmtpMailer;
+
+use std::env;
+use std::path::PathBuf;
+
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Outgoing mail transport, managed by Rocket as `Box<dyn Mailer>`.
+pub trait Mailer: Send + Sync {
+    fn send(&self, mail: Mail) -> Result<(), String>;
+}
+
+/// Picks the transport from `MAIL_TRANSPORT`: `smtp`, `file` or `stdout` (the default).
+pub fn from_env() -> Result<Box<dyn Mailer>, String> {
+    let from = env::var("MAIL_FROM").unwrap_or("Conduit <noreply@conduit.local>".to_owned());
+    match env::var("MAIL_TRANSPORT")
+        .unwrap_or("stdout".to_owned())
+        .as_str()
+    {
+        "smtp" => SmtpMailer::from_env(from).map(|m| Box::new(m) as Box<dyn Mailer>),
+        "file" => {
+            let path = env::var("MAIL_FILE").map_err(|err| {
+                format!("MAIL_FILE environment variable required for the file transport: {}", err)
+            })?;
+            Ok(Box::new(FileMailer::new(from, Some(PathBuf::from(path)))))
+        }
+        "stdout" => Ok(Box::new(FileMailer::new(from, None))),
+        other => Err(format!("Unknown MAIL_TRANSPORT: {}", other)),
+    }
+}
+
+pub fn password_reset(to: &str, token: &str, app_url: &str) -> Mail {
+    Mail {
+        to: to.to_owned(),
+        subject: "Reset your Conduit password".to_owned(),
+        body: format!(
+            "Someone asked to reset the password of your Conduit account.\n\n\
+             Use the following link to choose a new one:\n{}/reset-password?token={}\n\n\
+             If you didn't request this, you can safely ignore this email.",
+            app_url, token
+        ),
+    }
+}
+
+pub fn email_verification(to: &str, token: &str, app_url: &str) -> Mail {
+    Mail {
+        to: to.to_owned(),
+        subject: "Confirm your Conduit email address".to_owned(),
+        body: format!(
+            "Please confirm that this address belongs to you by following this link:\n\
+             {}/verify-email?token={}\n\n\
+             If you didn't create a Conduit account, you can safely ignore this email.",
+            app_url, token
+        ),
+    }
+}
@@ -0,0 +1,51 @@
+use super::{Mail, Mailer};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use std::env;
+
+pub struct SmtpMailer {
+    from: Mailbox,
+    transport: SmtpTransport,
+}
+
+impl SmtpMailer {
+    pub fn from_env(from: String) -> Result<SmtpMailer, String> {
+        let host = env::var("SMTP_HOST")
+            .map_err(|err| format!("SMTP_HOST environment variable required: {}", err))?;
+        let mut builder = SmtpTransport::relay(&host)
+            .map_err(|err| format!("SMTP configuration failed: {}", err))?;
+        if let Ok(port_s) = env::var("SMTP_PORT") {
+            let port = port_s
+                .parse::<u16>()
+                .map_err(|err| format!("SMTP port parsing failed: {}", err))?;
+            builder = builder.port(port);
+        }
+        if let (Ok(username), Ok(password)) = (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD"))
+        {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+        Ok(SmtpMailer {
+            from: from
+                .parse()
+                .map_err(|err| format!("MAIL_FROM is invalid: {}", err))?,
+            transport: builder.build(),
+        })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, mail: Mail) -> Result<(), String> {
+        let to: Mailbox = mail.to.parse().map_err(|err| format!("{}", err))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(mail.subject)
+            .body(mail.body)
+            .map_err(|err| err.to_string())?;
+        self.transport
+            .send(&message)
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
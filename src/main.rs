@@ -1,13 +1,21 @@
 #![feature(proc_macro_hygiene, decl_macro)]
 
 mod authentication;
+mod client_ip;
+mod conditional;
 mod config;
+mod cursor;
 mod db;
 mod errors;
 mod format;
+mod jwt_keys;
+mod mailer;
 mod models;
+mod password;
 mod routes;
 mod schema;
+mod tokens;
+mod totp;
 
 #[macro_use]
 extern crate rocket;
@@ -17,6 +25,8 @@ extern crate rocket_contrib;
 extern crate serde_derive;
 #[macro_use]
 extern crate diesel;
+#[macro_use]
+extern crate log;
 
 use crate::config::{configure_rocket, Config};
 use crate::db::DbConnection;
@@ -43,7 +53,11 @@ fn forbidden(_: &Request) -> Error {
 }
 
 fn cors_options() -> CorsOptions {
-    CorsOptions::default()
+    CorsOptions {
+        // Needed by browser clients to send If-Match on updates
+        expose_headers: ["ETag"].iter().map(ToString::to_string).collect(),
+        ..CorsOptions::default()
+    }
 }
 
 fn main() -> Result<(), String> {
@@ -53,8 +67,10 @@ fn main() -> Result<(), String> {
 
     let cors = cors_options().to_cors().map_err(|err| err.to_string())?;
     let config = Config::from_env()?;
+    let mailer = mailer::from_env()?;
     rocket::custom(configure_rocket()?)
         .manage(config)
+        .manage(mailer)
         .mount(
             "/api",
             routes![
@@ -62,10 +78,25 @@ fn main() -> Result<(), String> {
                 routes::articles::article,
                 routes::articles::articles,
                 routes::users::login,
+                routes::users::login_second_factor,
                 routes::users::register,
+                routes::users::refresh_token,
+                routes::users::logout,
+                routes::users::logout_everywhere,
+                routes::users::request_password_reset,
+                routes::users::confirm_password_reset,
+                routes::users::verify_email,
+                routes::users::resend_verification,
+                routes::two_factor::enroll,
+                routes::two_factor::confirm,
+                routes::two_factor::disable,
+                routes::api_keys::api_keys,
+                routes::api_keys::new_api_key,
+                routes::api_keys::revoke_api_key,
                 routes::users::current_user,
                 routes::users::update_current_user,
                 routes::users::profile,
+                routes::users::profiles,
                 routes::users::follow,
                 routes::users::unfollow,
                 routes::articles::favorite,
@@ -75,9 +106,26 @@ fn main() -> Result<(), String> {
                 routes::comments::delete_comment,
                 routes::articles::tags,
                 routes::articles::feed,
+                routes::articles::search,
                 routes::articles::new_article,
                 routes::articles::update_article,
-                routes::articles::delete_article
+                routes::articles::delete_article,
+                routes::articles::drafts,
+                routes::articles::publish,
+                routes::articles::revisions,
+                routes::articles::revision,
+                routes::articles::revision_diff,
+                routes::articles::restore_revision,
+                routes::admin::users,
+                routes::admin::user,
+                routes::admin::suspend_user,
+                routes::admin::unsuspend_user,
+                routes::admin::change_role,
+                routes::admin::force_password_reset,
+                routes::admin::delete_user,
+                routes::admin::unlock_user,
+                routes::admin::audit_events,
+                routes::jwks::jwks
             ],
         )
         .register(catchers![forbidden, unauthorized])
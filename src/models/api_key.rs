@@ -0,0 +1,73 @@
+use crate::format::encode_datetime;
+use chrono::NaiveDateTime;
+use rocket::response;
+use rocket::response::Responder;
+use rocket::Request;
+
+/// Scopes an API key can be restricted to. Reading is always allowed.
+pub const SCOPES: &[&str] = &[
+    "articles:write",
+    "comments:write",
+    "favorites:write",
+    "follows:write",
+    "user:write",
+];
+
+#[derive(Serialize)]
+pub struct ApiKey {
+    pub id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Option<Vec<String>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<String>,
+    /// Only returned once, when the key is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+#[derive(Queryable)]
+pub struct ApiKeyQuery {
+    pub id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Option<Vec<String>>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+impl ApiKeyQuery {
+    pub fn to_api_key(self, key: Option<String>) -> ApiKey {
+        ApiKey {
+            id: self.id,
+            name: self.name,
+            prefix: self.prefix,
+            scopes: self.scopes,
+            created_at: encode_datetime(self.created_at),
+            last_used_at: self.last_used_at.map(encode_datetime),
+            key,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewApiKeyData {
+    pub name: String,
+    pub scopes: Option<Vec<String>>,
+}
+
+pub struct ApiKeyList(pub Vec<ApiKey>);
+
+impl<'r> Responder<'r> for ApiKeyList {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        json![{ "apiKeys": self.0 }].respond_to(req)
+    }
+}
+
+impl<'r> Responder<'r> for ApiKey {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        json![{ "apiKey": self }].respond_to(req)
+    }
+}
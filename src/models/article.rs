@@ -1,6 +1,10 @@
 use crate::models::user::Profile;
+use crate::tokens;
 use crate::schema::articles;
-use chrono::NaiveDateTime;
+use crate::conditional;
+use crate::config::{SlugOptions, SlugStrategy};
+use crate::format::{decode_datetime, encode_datetime};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use rocket::response;
 use rocket::response::Responder;
 use rocket::Request;
@@ -9,6 +13,8 @@ use slug;
 use rand::distributions::Alphanumeric;
 use rand::*;
 const SUFFIX_LEN: usize = 8;
+/// Slugs that would be shadowed by other routes under `/articles`.
+pub const RESERVED_SLUGS: &[&str] = &["feed", "search"];
 
 #[derive(Serialize)]
 pub struct Article {
@@ -26,6 +32,84 @@ pub struct Article {
     #[serde(rename = "favoritesCount")]
     pub favorites_count: i32,
     pub author: Profile,
+    pub status: String,
+    #[serde(rename = "publishedAt")]
+    pub published_at: Option<String>,
+}
+
+/// Publication state of an article. Unlisted articles are only reachable through their slug.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ArticleStatus {
+    Draft,
+    Published,
+    Unlisted,
+}
+
+impl ArticleStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArticleStatus::Draft => "draft",
+            ArticleStatus::Published => "published",
+            ArticleStatus::Unlisted => "unlisted",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<ArticleStatus> {
+        match status {
+            "draft" => Some(ArticleStatus::Draft),
+            "published" => Some(ArticleStatus::Published),
+            "unlisted" => Some(ArticleStatus::Unlisted),
+            _ => None,
+        }
+    }
+}
+
+/// Order of article listings, the most recent first unless `Oldest`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ArticleSort {
+    Newest,
+    Oldest,
+    MostFavorited,
+    MostCommented,
+    RecentlyUpdated,
+}
+
+impl ArticleSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArticleSort::Newest => "newest",
+            ArticleSort::Oldest => "oldest",
+            ArticleSort::MostFavorited => "favorited",
+            ArticleSort::MostCommented => "commented",
+            ArticleSort::RecentlyUpdated => "updated",
+        }
+    }
+
+    pub fn parse(sort: &str) -> Option<ArticleSort> {
+        match sort {
+            "newest" => Some(ArticleSort::Newest),
+            "oldest" => Some(ArticleSort::Oldest),
+            "favorited" => Some(ArticleSort::MostFavorited),
+            "commented" => Some(ArticleSort::MostCommented),
+            "updated" => Some(ArticleSort::RecentlyUpdated),
+            _ => None,
+        }
+    }
+}
+
+/// Criteria of an article listing, empty lists meaning no restriction. Articles
+/// have any of `tags`, or all of them with `all_tags`, none of `excluded_tags` and
+/// are written by one of `authors`. The publication range includes `since` and
+/// excludes `until`.
+#[derive(Default)]
+pub struct ArticleFilter {
+    pub tags: Vec<String>,
+    pub all_tags: bool,
+    pub excluded_tags: Vec<String>,
+    pub authors: Vec<String>,
+    pub favorited: Option<String>,
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
 }
 
 #[derive(Queryable, Identifiable, Associations)]
@@ -41,6 +125,8 @@ pub struct PGArticle {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub favorites_count: i32,
+    pub status: String,
+    pub published_at: Option<NaiveDateTime>,
 }
 
 impl PGArticle {
@@ -53,6 +139,8 @@ impl PGArticle {
             title,
             description,
             favorites_count,
+            status,
+            published_at,
             ..
         } = self;
         Article {
@@ -66,6 +154,8 @@ impl PGArticle {
             favorited,
             tag_list,
             author: profile,
+            status,
+            published_at: published_at.map(encode_datetime),
         }
     }
 }
@@ -77,6 +167,11 @@ pub struct NewArticleData {
     pub body: String,
     #[serde(rename = "tagList")]
     pub tag_list: Option<Vec<String>>,
+    /// Defaults to published
+    pub status: Option<String>,
+    /// Publication date, in the future to schedule the article. Defaults to now.
+    #[serde(rename = "publishAt")]
+    pub publish_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Deserialize)]
@@ -86,38 +181,203 @@ pub struct UpdateArticleData {
     pub body: Option<String>,
     #[serde(rename = "tagList")]
     pub tag_list: Option<Vec<String>>,
+    pub status: Option<String>,
+    #[serde(rename = "publishAt")]
+    pub publish_at: Option<DateTime<Utc>>,
 }
 
 pub struct TagList(pub Vec<String>);
 
 impl<'r> Responder<'r> for TagList {
     fn respond_to(self, req: &Request) -> response::Result<'r> {
-        json![{ "tags": self.0 }].respond_to(req)
+        conditional::respond(req, json![{ "tags": self.0 }], None, None)
     }
 }
 
 pub struct ArticleList {
     pub articles: Vec<Article>,
     pub article_count: i64,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
 }
 
 impl<'r> Responder<'r> for ArticleList {
     fn respond_to(self, req: &Request) -> response::Result<'r> {
-        json![{ "articles": self.articles, "articlesCount": self.article_count }].respond_to(req)
+        conditional::respond(
+            req,
+            json![{
+                "articles": self.articles,
+                "articlesCount": self.article_count,
+                "nextCursor": self.next_cursor,
+                "prevCursor": self.prev_cursor,
+            }],
+            None,
+            None,
+        )
+    }
+}
+
+/// Article matching a search, along with its fields where the matched terms are
+/// wrapped in `<mark>` tags. Only excerpts of the body are kept.
+#[derive(Serialize)]
+pub struct ArticleMatch {
+    #[serde(flatten)]
+    pub article: Article,
+    pub highlight: Highlight,
+}
+
+#[derive(Serialize)]
+pub struct Highlight {
+    pub title: String,
+    pub description: String,
+    pub body: String,
+}
+
+pub struct ArticleMatchList {
+    pub articles: Vec<ArticleMatch>,
+    pub article_count: i64,
+}
+
+impl<'r> Responder<'r> for ArticleMatchList {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        conditional::respond(
+            req,
+            json![{ "articles": self.articles, "articlesCount": self.article_count }],
+            None,
+            None,
+        )
+    }
+}
+
+impl Article {
+    /// Changes whenever the article is modified, favorites aside.
+    pub fn version(&self) -> String {
+        tokens::hash(&format!("{}:{}", self.slug, self.updated_at))[..32].to_owned()
+    }
+
+    pub fn last_modified(&self) -> Option<NaiveDateTime> {
+        decode_datetime(&self.updated_at)
     }
 }
 
 impl<'r> Responder<'r> for Article {
     fn respond_to(self, req: &Request) -> response::Result<'r> {
-        json![{ "article": self }].respond_to(req)
+        let version = self.version();
+        let last_modified = self.last_modified();
+        let body = json![{ "article": self }];
+        let etag = conditional::versioned_etag(&version, &body);
+        conditional::respond(req, body, Some(etag), last_modified)
     }
 }
 
-pub fn slugify(title: &str) -> String {
-    format!("{}-{}", slug::slugify(title), generate_suffix(SUFFIX_LEN))
+/// Slug of an article titled `title` and created on `date`. With the clean strategy,
+/// `numbered_slug` gives the alternatives to use when it is already taken.
+pub fn slugify(title: &str, date: NaiveDate, options: &SlugOptions) -> String {
+    // Non-Latin titles are transliterated, and those made only of symbols get a random slug
+    let mut slug = slug::slugify(title);
+    if slug.is_empty() {
+        slug = generate_suffix(SUFFIX_LEN).to_lowercase();
+    }
+    if options.date_prefix {
+        slug = format!("{}-{}", date.format("%Y-%m-%d"), slug);
+    }
+    match options.strategy {
+        SlugStrategy::Clean => truncate(&slug, options.max_length).to_owned(),
+        SlugStrategy::Random => format!(
+            "{}-{}",
+            truncate(&slug, options.max_length - SUFFIX_LEN - 1),
+            generate_suffix(SUFFIX_LEN)
+        ),
+    }
+}
+
+/// `slug` followed by `-n`, shortened to stay within `max_length`.
+pub fn numbered_slug(slug: &str, n: u32, max_length: usize) -> String {
+    let suffix = format!("-{}", n);
+    format!("{}{}", truncate(slug, max_length - suffix.len()), suffix)
+}
+
+// Cuts between words when that doesn't lose too much of the slug
+fn truncate(slug: &str, max_length: usize) -> &str {
+    if slug.len() <= max_length {
+        return slug;
+    }
+    // Slugs are transliterated to ASCII, but a cut must not split a character anyway
+    let end = (0..=max_length)
+        .rev()
+        .find(|i| slug.is_char_boundary(*i))
+        .unwrap_or(0);
+    let cut = &slug[..end];
+    match cut.rfind('-') {
+        Some(i) if i > end / 2 => &cut[..i],
+        _ => cut.trim_end_matches('-'),
+    }
 }
 
 fn generate_suffix(len: usize) -> String {
     let mut rng = thread_rng();
     (0..len).map(|_| rng.sample(Alphanumeric)).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(strategy: SlugStrategy, max_length: usize) -> SlugOptions {
+        SlugOptions {
+            strategy,
+            date_prefix: false,
+            max_length,
+        }
+    }
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 18).unwrap()
+    }
+
+    #[test]
+    fn reserves_route_segments() {
+        let clean = options(SlugStrategy::Clean, 80);
+        assert!(RESERVED_SLUGS.contains(&slugify("Feed", date(), &clean).as_str()));
+        assert!(RESERVED_SLUGS.contains(&slugify("  SEARCH! ", date(), &clean).as_str()));
+        assert!(!RESERVED_SLUGS.contains(&slugify("Feeds", date(), &clean).as_str()));
+    }
+
+    #[test]
+    fn transliterates_and_prefixes() {
+        let mut prefixed = options(SlugStrategy::Clean, 80);
+        prefixed.date_prefix = true;
+        assert_eq!(slugify("Ça va ?", date(), &prefixed), "2026-10-18-ca-va");
+    }
+
+    #[test]
+    fn cuts_between_words() {
+        assert_eq!(truncate("abc-defgh-ijklm", 13), "abc-defgh");
+        assert_eq!(truncate("abcdefghijklm", 5), "abcde");
+        assert_eq!(truncate("abc", 5), "abc");
+    }
+
+    #[test]
+    fn cuts_at_character_boundaries() {
+        assert_eq!(truncate("éééé", 3), "é");
+        assert_eq!(truncate("é-ééé", 6), "é-é");
+        let slug = slugify(&"Ünïcödé ".repeat(20), date(), &options(SlugStrategy::Clean, 32));
+        assert!(slug.is_ascii() && slug.len() <= 32, "{}", slug);
+    }
+
+    #[test]
+    fn keeps_numbered_slugs_within_max_length() {
+        assert_eq!(numbered_slug("abc-defgh-ijklm", 12, 16), "abc-defgh-12");
+        assert_eq!(numbered_slug("abc", 2, 16), "abc-2");
+        let long = "a".repeat(40);
+        let numbered = numbered_slug(&long, 1000, 32);
+        assert_eq!(numbered.len(), 32);
+        assert!(numbered.ends_with("-1000"));
+    }
+
+    #[test]
+    fn keeps_random_slugs_within_max_length() {
+        let slug = slugify(&"word ".repeat(20), date(), &options(SlugStrategy::Random, 32));
+        assert!(slug.len() <= 32, "{}", slug);
+    }
+}
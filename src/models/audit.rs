@@ -0,0 +1,71 @@
+use crate::format::encode_datetime;
+use chrono::NaiveDateTime;
+use rocket::response;
+use rocket::response::Responder;
+use rocket::Request;
+use serde_json::Value;
+
+#[derive(Serialize)]
+pub struct AuditEvent {
+    pub id: i32,
+    #[serde(rename = "actorId")]
+    pub actor_id: Option<i32>,
+    pub action: String,
+    #[serde(rename = "targetType")]
+    pub target_type: String,
+    #[serde(rename = "targetId")]
+    pub target_id: String,
+    pub ip: Option<String>,
+    pub diff: Value,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+#[derive(Queryable)]
+pub struct AuditEventQuery {
+    pub id: i32,
+    pub actor_id: Option<i32>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: String,
+    pub ip: Option<String>,
+    pub diff: Value,
+    pub created_at: NaiveDateTime,
+}
+
+impl AuditEventQuery {
+    pub fn to_audit_event(self) -> AuditEvent {
+        AuditEvent {
+            id: self.id,
+            actor_id: self.actor_id,
+            action: self.action,
+            target_type: self.target_type,
+            target_id: self.target_id,
+            ip: self.ip,
+            diff: self.diff,
+            created_at: encode_datetime(self.created_at),
+        }
+    }
+}
+
+/// Criteria of an audit log search, all optional. The time range includes `from`
+/// and excludes `until`.
+pub struct AuditFilter {
+    pub actor_id: Option<i32>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub from: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+}
+
+pub struct AuditEventList {
+    pub events: Vec<AuditEvent>,
+    pub events_count: i64,
+}
+
+impl<'r> Responder<'r> for AuditEventList {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        json![{ "auditEvents": self.events, "auditEventsCount": self.events_count }]
+            .respond_to(req)
+    }
+}
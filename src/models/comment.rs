@@ -1,4 +1,5 @@
-use crate::format::encode_datetime;
+use crate::conditional;
+use crate::format::{decode_datetime, encode_datetime};
 use crate::models::user;
 use chrono::NaiveDateTime;
 use rocket::response;
@@ -46,16 +47,29 @@ pub struct NewCommentData {
 pub struct CommentList {
     pub comments: Vec<Comment>,
     pub comments_count: i64,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
 }
 
 impl<'r> Responder<'r> for CommentList {
     fn respond_to(self, req: &Request) -> response::Result<'r> {
-        json![{ "comments": self.comments, "commentsCount": self.comments_count }].respond_to(req)
+        conditional::respond(
+            req,
+            json![{
+                "comments": self.comments,
+                "commentsCount": self.comments_count,
+                "nextCursor": self.next_cursor,
+                "prevCursor": self.prev_cursor,
+            }],
+            None,
+            None,
+        )
     }
 }
 
 impl<'r> Responder<'r> for Comment {
     fn respond_to(self, req: &Request) -> response::Result<'r> {
-        json![{ "comment": self }].respond_to(req)
+        let last_modified = decode_datetime(&self.updated_at);
+        conditional::respond(req, json![{ "comment": self }], None, last_modified)
     }
 }
@@ -1,3 +1,7 @@
+pub mod api_key;
+pub mod audit;
 pub mod user;
 pub mod article;
-pub mod comment;
\ No newline at end of file
+pub mod comment;
+pub mod revision;
+pub mod two_factor;
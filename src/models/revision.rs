@@ -0,0 +1,86 @@
+use crate::format::encode_datetime;
+use chrono::NaiveDateTime;
+use rocket::response;
+use rocket::response::Responder;
+use rocket::Request;
+
+#[derive(Serialize)]
+pub struct Revision {
+    pub revision: i32,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    #[serde(rename = "tagList")]
+    pub tag_list: Vec<String>,
+    /// Username of the editor, `None` once their account has been deleted
+    pub editor: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+#[derive(Queryable)]
+pub struct RevisionQuery {
+    pub revision: i32,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub tag_list: Vec<String>,
+    pub editor: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl RevisionQuery {
+    pub fn to_revision(self) -> Revision {
+        Revision {
+            revision: self.revision,
+            title: self.title,
+            description: self.description,
+            body: self.body,
+            tag_list: self.tag_list,
+            editor: self.editor,
+            created_at: encode_datetime(self.created_at),
+        }
+    }
+}
+
+/// Page of the revisions of an article, `revisions_count` counting all of them.
+pub struct RevisionList {
+    pub revisions: Vec<Revision>,
+    pub revisions_count: i64,
+}
+
+/// One line of a diff, `kind` being "added", "removed" or "unchanged".
+#[derive(Serialize)]
+pub struct DiffLine {
+    pub kind: &'static str,
+    pub line: String,
+}
+
+/// Line by line differences between two revisions of an article.
+#[derive(Serialize)]
+pub struct RevisionDiff {
+    pub from: i32,
+    pub to: i32,
+    pub title: Vec<DiffLine>,
+    pub description: Vec<DiffLine>,
+    pub body: Vec<DiffLine>,
+}
+
+impl<'r> Responder<'r> for RevisionList {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        json![{ "revisions": self.revisions, "revisionsCount": self.revisions_count }]
+            .respond_to(req)
+    }
+}
+
+impl<'r> Responder<'r> for Revision {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        json![{ "revision": self }].respond_to(req)
+    }
+}
+
+impl<'r> Responder<'r> for RevisionDiff {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        json![{ "diff": self }].respond_to(req)
+    }
+}
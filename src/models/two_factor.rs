@@ -0,0 +1,39 @@
+use rocket::response;
+use rocket::response::Responder;
+use rocket::Request;
+
+#[derive(Serialize)]
+pub struct TwoFactorSetup {
+    pub secret: String,
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+}
+
+#[derive(Serialize)]
+pub struct RecoveryCodes {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TwoFactorCode {
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct TwoFactorLoginData {
+    pub challenge: String,
+    pub code: String,
+}
+
+impl<'r> Responder<'r> for TwoFactorSetup {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        json![{ "twoFactor": self }].respond_to(req)
+    }
+}
+
+impl<'r> Responder<'r> for RecoveryCodes {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        json![{ "twoFactor": self }].respond_to(req)
+    }
+}
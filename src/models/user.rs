@@ -1,6 +1,9 @@
 use crate::authentication;
+use crate::config::Config;
 use crate::db::DbResult;
+use crate::format::encode_datetime;
 use crate::schema::users;
+use chrono::NaiveDateTime;
 
 #[derive(Serialize)]
 pub struct Username(pub String);
@@ -14,6 +17,38 @@ pub struct User {
     pub image: Option<String>,
     //#[serde(skip_serializing)]
     pub hash: String,
+    pub token_version: i32,
+    pub verified_at: Option<NaiveDateTime>,
+    pub role: String,
+    pub suspended_at: Option<NaiveDateTime>,
+}
+
+/// Roles are ordered, each one granting the permissions of the previous ones.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Moderator,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Moderator => "moderator",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<Role> {
+        match role {
+            "user" => Some(Role::User),
+            "moderator" => Some(Role::Moderator),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -22,11 +57,54 @@ pub struct AuthenticatedUser {
     pub email: String,
     pub bio: Option<String>,
     pub image: Option<String>,
-    pub token: String,
+    /// Left out for requests authenticated with an API key, see `without_token`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(rename = "refreshToken", skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    pub verified: bool,
     #[serde(skip_serializing)]
     pub id: i32,
 }
 
+impl AuthenticatedUser {
+    /// Account details without any credential, returned to API keys which could
+    /// otherwise be traded for a session token that isn't limited to their scopes.
+    pub fn without_token(self) -> AuthenticatedUser {
+        AuthenticatedUser {
+            token: None,
+            refresh_token: None,
+            ..self
+        }
+    }
+}
+
+/// Outcome of a password login: accounts with two factor authentication
+/// get a challenge to complete instead of a session.
+pub enum LoginResponse {
+    Authenticated(AuthenticatedUser),
+    SecondFactorRequired(String),
+}
+
+/// Account details shown to administrators.
+#[derive(Serialize)]
+pub struct ManagedUser {
+    pub id: i32,
+    pub username: String,
+    pub email: String,
+    pub bio: Option<String>,
+    pub image: Option<String>,
+    pub role: String,
+    pub verified: bool,
+    #[serde(rename = "suspendedAt")]
+    pub suspended_at: Option<String>,
+}
+
+pub struct ManagedUserList {
+    pub users: Vec<ManagedUser>,
+    pub users_count: i64,
+}
+
 #[derive(Serialize, Clone)]
 pub struct Profile {
     pub username: String,
@@ -35,6 +113,9 @@ pub struct Profile {
     pub following: bool,
 }
 
+/// Profiles suggested while typing a username.
+pub struct ProfileList(pub Vec<Profile>);
+
 impl User {
     pub fn to_profile(self, followed: bool) -> Profile {
         Profile {
@@ -45,14 +126,29 @@ impl User {
         }
     }
 
-    pub fn to_authenticated(self, secret: &String) -> DbResult<AuthenticatedUser> {
-        authentication::encode_token(self.id, &self.username, secret).map(|token| {
+    pub fn to_managed(self) -> ManagedUser {
+        ManagedUser {
+            id: self.id,
+            username: self.username,
+            email: self.email,
+            bio: self.bio,
+            image: self.image,
+            role: self.role,
+            verified: self.verified_at.is_some(),
+            suspended_at: self.suspended_at.map(encode_datetime),
+        }
+    }
+
+    pub fn to_authenticated(self, config: &Config) -> DbResult<AuthenticatedUser> {
+        authentication::encode_token(self.id, &self.username, self.token_version, config).map(|token| {
             AuthenticatedUser {
                 username: self.username,
                 bio: self.bio,
                 email: self.email,
                 image: self.image,
-                token: token,
+                token: Some(token),
+                refresh_token: None,
+                verified: self.verified_at.is_some(),
                 id: self.id,
             }
         })
@@ -75,6 +171,33 @@ pub struct UserUpdateData {
     pub image: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct RefreshData {
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetData {
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct VerificationData {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct RoleData {
+    pub role: String,
+}
+
 #[derive(Deserialize)]
 pub struct LoginData {
     pub email: String,
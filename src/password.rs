@@ -0,0 +1,59 @@
+use crate::config::PasswordHashing;
+use crate::errors::Error;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use std::convert::TryFrom;
+
+/// Prefix of the hashes produced by `scrypt::scrypt_simple`, used before argon2id.
+const SCRYPT_PREFIX: &str = "$rscrypt$";
+const ARGON2ID: &str = "argon2id";
+
+pub fn hash(password: &str, config: &PasswordHashing) -> Result<String, Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    hasher(config)?
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(hashing_error)
+}
+
+/// Checks a password against a stored hash, whichever algorithm produced it.
+pub fn verify(password: &str, stored: &str) -> Result<bool, Error> {
+    if stored.starts_with(SCRYPT_PREFIX) {
+        return Ok(scrypt::scrypt_check(password, stored).is_ok());
+    }
+    let parsed = PasswordHash::new(stored).map_err(hashing_error)?;
+    Ok(Argon2::default()
+        .verify_password(password.as_bytes(), &parsed)
+        .is_ok())
+}
+
+/// Whether the stored hash was produced by another algorithm or with other costs
+/// than the ones currently configured.
+pub fn needs_rehash(stored: &str, config: &PasswordHashing) -> bool {
+    let parsed = match PasswordHash::new(stored) {
+        Ok(parsed) => parsed,
+        Err(_) => return true,
+    };
+    if parsed.algorithm.as_str() != ARGON2ID || parsed.version != Some(Version::V0x13.into()) {
+        return true;
+    }
+    match Params::try_from(&parsed) {
+        Ok(params) => {
+            params.m_cost() != config.memory_cost
+                || params.t_cost() != config.time_cost
+                || params.p_cost() != config.parallelism
+        }
+        Err(_) => true,
+    }
+}
+
+fn hasher(config: &PasswordHashing) -> Result<Argon2<'static>, Error> {
+    Params::new(config.memory_cost, config.time_cost, config.parallelism, None)
+        .map(|params| Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+        .map_err(hashing_error)
+}
+
+fn hashing_error<E: ToString>(err: E) -> Error {
+    Error::InternalServerError("password".to_owned(), err.to_string())
+}
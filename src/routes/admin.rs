@@ -0,0 +1,236 @@
+use crate::authentication::AdminUser;
+use crate::client_ip::ClientIp;
+use crate::config::Config;
+use crate::db;
+use crate::db::{DbConnection, DbResult};
+use crate::errors::Error;
+use crate::format::decode_rfc3339;
+use crate::mailer;
+use crate::mailer::Mailer;
+use crate::models::audit::{AuditEventList, AuditFilter};
+use crate::models::user::{ManagedUser, ManagedUserList, Role, RoleData};
+use chrono::NaiveDateTime;
+use diesel::Connection;
+use rocket::request::LenientForm;
+use rocket::response;
+use rocket::response::status::NoContent;
+use rocket::response::Responder;
+use rocket::Request;
+use rocket::State;
+use rocket_contrib::json::Json;
+
+/// Filters of the audit log. `from` and `until` are RFC 3339 timestamps.
+#[derive(FromForm)]
+pub struct AuditFilterParams {
+    actor: Option<i32>,
+    target_type: Option<String>,
+    target_id: Option<String>,
+    from: Option<String>,
+    until: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RoleWrapper {
+    user: RoleData,
+}
+
+impl<'r> Responder<'r> for ManagedUser {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        json![{ "user": self }].respond_to(req)
+    }
+}
+
+impl<'r> Responder<'r> for ManagedUserList {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        json![{ "users": self.users, "usersCount": self.users_count }].respond_to(req)
+    }
+}
+
+#[get("/admin/users?<search>&<role>&<suspended>&<limit>&<offset>")]
+pub fn users(
+    conn: DbConnection,
+    _admin: AdminUser,
+    search: Option<String>,
+    role: Option<String>,
+    suspended: Option<bool>,
+    limit: Option<i32>,
+    offset: Option<i32>,
+) -> DbResult<ManagedUserList> {
+    let role = role.map(|r| parse_role(&r)).transpose()?;
+    db::users::list(&conn, &search, &role, suspended, limit, offset)
+}
+
+#[get("/admin/users/<username>")]
+pub fn user(conn: DbConnection, _admin: AdminUser, username: String) -> DbResult<ManagedUser> {
+    db::users::find_by_username(&conn, &username).map(|user| user.to_managed())
+}
+
+#[post("/admin/users/<username>/suspend")]
+pub fn suspend_user(
+    conn: DbConnection,
+    admin: AdminUser,
+    username: String,
+    client_ip: ClientIp,
+) -> DbResult<ManagedUser> {
+    not_self(&admin, &username)?;
+    conn.transaction(|| {
+        let before = db::users::find_by_username(&conn, &username)?.to_managed();
+        let after = db::users::set_suspended(&conn, &username, true)?.to_managed();
+        record(&conn, &admin, &client_ip, "suspend_user", &before, Some(&after))?;
+        Ok(after)
+    })
+}
+
+#[post("/admin/users/<username>/unsuspend")]
+pub fn unsuspend_user(
+    conn: DbConnection,
+    admin: AdminUser,
+    username: String,
+    client_ip: ClientIp,
+) -> DbResult<ManagedUser> {
+    conn.transaction(|| {
+        let before = db::users::find_by_username(&conn, &username)?.to_managed();
+        let after = db::users::set_suspended(&conn, &username, false)?.to_managed();
+        record(&conn, &admin, &client_ip, "unsuspend_user", &before, Some(&after))?;
+        Ok(after)
+    })
+}
+
+#[put("/admin/users/<username>/role", data = "<data>", format = "json")]
+pub fn change_role(
+    conn: DbConnection,
+    admin: AdminUser,
+    username: String,
+    data: Json<RoleWrapper>,
+    client_ip: ClientIp,
+) -> DbResult<ManagedUser> {
+    not_self(&admin, &username)?;
+    let role = parse_role(&data.user.role)?;
+    conn.transaction(|| {
+        let before = db::users::find_by_username(&conn, &username)?.to_managed();
+        let after = db::users::set_role(&conn, &username, role)?.to_managed();
+        record(&conn, &admin, &client_ip, "change_role", &before, Some(&after))?;
+        Ok(after)
+    })
+}
+
+/// Locks the user out until they pick a new password from the emailed reset link.
+#[post("/admin/users/<username>/password-reset")]
+pub fn force_password_reset(
+    conn: DbConnection,
+    admin: AdminUser,
+    username: String,
+    client_ip: ClientIp,
+    config: State<Config>,
+    transport: State<Box<dyn Mailer>>,
+) -> DbResult<NoContent> {
+    let (user, token) = conn.transaction::<_, Error, _>(|| {
+        let (user, token) = db::password_resets::force(&conn, &username, &config)?;
+        let user = user.to_managed();
+        record(&conn, &admin, &client_ip, "force_password_reset", &user, None)?;
+        Ok((user, token))
+    })?;
+    transport
+        .send(mailer::password_reset(&user.email, &token, &config.app_url))
+        .map(|_| NoContent)
+        .map_err(|err| Error::InternalServerError("mail".to_owned(), err))
+}
+
+#[delete("/admin/users/<username>")]
+pub fn delete_user(
+    conn: DbConnection,
+    admin: AdminUser,
+    username: String,
+    client_ip: ClientIp,
+) -> DbResult<NoContent> {
+    not_self(&admin, &username)?;
+    conn.transaction(|| {
+        let before = db::users::find_by_username(&conn, &username)?.to_managed();
+        db::users::delete(&conn, &username)?;
+        record(&conn, &admin, &client_ip, "delete_user", &before, None)
+    })
+    .map(|_| NoContent)
+}
+
+#[post("/admin/users/<username>/unlock")]
+pub fn unlock_user(
+    conn: DbConnection,
+    admin: AdminUser,
+    username: String,
+    client_ip: ClientIp,
+) -> DbResult<NoContent> {
+    conn.transaction(|| {
+        db::users::unlock(&conn, &username)?;
+        let user = db::users::find_by_username(&conn, &username)?.to_managed();
+        record(&conn, &admin, &client_ip, "unlock_user", &user, None)
+    })
+    .map(|_| NoContent)
+}
+
+/// Audit log, most recent events first.
+#[get("/admin/audit?<limit>&<offset>&<filters..>")]
+pub fn audit_events(
+    conn: DbConnection,
+    _admin: AdminUser,
+    limit: Option<i32>,
+    offset: Option<i32>,
+    filters: LenientForm<AuditFilterParams>,
+) -> DbResult<AuditEventList> {
+    let filters = filters.into_inner();
+    let filter = AuditFilter {
+        actor_id: filters.actor,
+        target_type: filters.target_type,
+        target_id: filters.target_id,
+        from: filters.from.map(|t| parse_timestamp("from", &t)).transpose()?,
+        until: filters.until.map(|t| parse_timestamp("until", &t)).transpose()?,
+    };
+    db::audit::search(&conn, &filter, limit, offset)
+}
+
+// Changes made by admins are recorded as a diff of the account as they see it,
+// `after` being `None` when only the action itself matters. Runs in the transaction
+// of the change, which doesn't happen unless it is recorded.
+fn record(
+    conn: &DbConnection,
+    admin: &AdminUser,
+    client_ip: &ClientIp,
+    action: &str,
+    before: &ManagedUser,
+    after: Option<&ManagedUser>,
+) -> DbResult<()> {
+    let changes = match after {
+        Some(after) => db::audit::diff(&json!(before).0, &json!(after).0),
+        None => json!({}).0,
+    };
+    db::audit::record(
+        conn,
+        admin.0.id,
+        client_ip.0,
+        action,
+        "user",
+        &before.id.to_string(),
+        changes,
+    )
+}
+
+fn parse_timestamp(field: &str, value: &str) -> DbResult<NaiveDateTime> {
+    decode_rfc3339(value)
+        .ok_or_else(|| Error::ValidationFailed(json!({ field: "must be an RFC 3339 timestamp" })))
+}
+
+fn parse_role(role: &str) -> DbResult<Role> {
+    Role::parse(role).ok_or(Error::ValidationFailed(
+        json![{"role": "must be one of user, moderator or admin"}],
+    ))
+}
+
+// Admins can't lock themselves out by mistake
+fn not_self(admin: &AdminUser, username: &String) -> DbResult<()> {
+    if &admin.0.username == username {
+        Err(Error::ValidationFailed(
+            json![{"user": "can't be your own account"}],
+        ))
+    } else {
+        Ok(())
+    }
+}
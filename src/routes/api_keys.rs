@@ -0,0 +1,53 @@
+use crate::authentication::AuthData;
+use crate::db;
+use crate::db::{DbConnection, DbResult};
+use crate::errors::Error;
+use crate::models::api_key::{ApiKey, ApiKeyList, NewApiKeyData, SCOPES};
+use rocket::response::status::NoContent;
+use rocket_contrib::json::Json;
+
+#[derive(Deserialize)]
+pub struct ApiKeyWrapper {
+    #[serde(rename = "apiKey")]
+    api_key: NewApiKeyData,
+}
+
+#[get("/user/api-keys")]
+pub fn api_keys(conn: DbConnection, auth: AuthData) -> DbResult<ApiKeyList> {
+    auth.require_session()?;
+    db::api_keys::list(&conn, auth.id)
+}
+
+#[post("/user/api-keys", data = "<data>", format = "json")]
+pub fn new_api_key(
+    conn: DbConnection,
+    auth: AuthData,
+    data: Json<ApiKeyWrapper>,
+) -> DbResult<ApiKey> {
+    auth.require_session()?;
+    let api_key = &data.api_key;
+    let mut errors = json![{}];
+    let mut error = false;
+    if api_key.name.is_empty() {
+        errors["name"] = json!["is empty"].0;
+        error = true;
+    }
+    if let Some(scopes) = &api_key.scopes {
+        if scopes.iter().any(|scope| !SCOPES.contains(&scope.as_str())) {
+            errors["scopes"] = json![format!["must be among {}", SCOPES.join(", ")]].0;
+            error = true;
+        }
+    }
+
+    if error {
+        Err(Error::ValidationFailed(errors))
+    } else {
+        db::api_keys::create(&conn, auth.id, &api_key.name, &api_key.scopes)
+    }
+}
+
+#[delete("/user/api-keys/<id>")]
+pub fn revoke_api_key(conn: DbConnection, auth: AuthData, id: i32) -> DbResult<NoContent> {
+    auth.require_session()?;
+    db::api_keys::revoke(&conn, auth.id, id).map(|_| NoContent)
+}
@@ -1,9 +1,24 @@
 use crate::authentication::AuthData;
+use crate::client_ip::ClientIp;
+use crate::conditional::IfMatch;
+use crate::config::Config;
+use crate::cursor::Cursor;
 use crate::db;
 use crate::db::DbConnection;
 use crate::errors::Error;
-use crate::models::article::{Article, ArticleList, NewArticleData, TagList, UpdateArticleData};
+use crate::format::decode_rfc3339;
+use crate::models::article::{
+    Article, ArticleFilter, ArticleList, ArticleMatchList, ArticleSort, ArticleStatus,
+    NewArticleData, TagList, UpdateArticleData,
+};
+use crate::models::revision::{Revision, RevisionDiff, RevisionList};
+use crate::models::user::Role;
+use chrono::{NaiveDate, NaiveDateTime};
 use db::DbResult;
+use rocket::request::{FromQuery, Query};
+use rocket::response;
+use rocket::response::{Redirect, Responder};
+use rocket::{Request, State};
 use rocket_contrib::json::Json;
 
 #[derive(Deserialize)]
@@ -11,24 +26,89 @@ pub struct ArticleWrapper<T> {
     article: T,
 }
 
-#[get("/articles?<tag>&<author>&<offset>&<limit>&<favorited>")]
+/// Filters of the article listing whose parameters can be repeated, namely `tag`,
+/// `excludeTag` and `author`, along with `tagMatch`, `favorited`, `since` and `until`.
+#[derive(Default)]
+pub struct ArticleFilterParams {
+    tags: Vec<String>,
+    tag_match: Option<String>,
+    excluded_tags: Vec<String>,
+    authors: Vec<String>,
+    favorited: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+}
+
+impl<'q> FromQuery<'q> for ArticleFilterParams {
+    type Error = ();
+
+    fn from_query(query: Query<'q>) -> Result<Self, Self::Error> {
+        let mut params = ArticleFilterParams::default();
+        for item in query {
+            let (key, value) = item.key_value_decoded();
+            match key.as_str() {
+                "tag" => params.tags.push(value),
+                "tagMatch" => params.tag_match = Some(value),
+                "excludeTag" => params.excluded_tags.push(value),
+                "author" => params.authors.push(value),
+                "favorited" => params.favorited = Some(value),
+                "since" => params.since = Some(value),
+                "until" => params.until = Some(value),
+                _ => {}
+            }
+        }
+        Ok(params)
+    }
+}
+
+/// Articles having any of the `tag`s, or all of them with `tagMatch=all`, none of
+/// the `excludeTag`s, by one of the `author`s, `favorited` by a user and published
+/// from `since` until `until` excluded, which are dates or RFC 3339 timestamps.
+///
+/// `cursor` takes the `nextCursor` or `prevCursor` of a previous page obtained with
+/// the same `sort`, in which case `offset` is ignored.
+#[get("/articles?<offset>&<limit>&<sort>&<cursor>&<filters..>")]
 pub fn articles(
     conn: DbConnection,
     auth: Option<AuthData>,
-    tag: Option<String>,
-    author: Option<String>,
     offset: Option<i32>,
     limit: Option<i32>,
-    favorited: Option<String>,
+    sort: Option<String>,
+    cursor: Option<String>,
+    filters: ArticleFilterParams,
 ) -> DbResult<ArticleList> {
+    let sort = match sort {
+        Some(sort) => ArticleSort::parse(&sort).ok_or(Error::ValidationFailed(json![{
+            "sort": "must be one of newest, oldest, favorited, commented or updated"
+        }]))?,
+        None => ArticleSort::Newest,
+    };
+    let all_tags = match filters.tag_match.as_deref() {
+        None | Some("any") => false,
+        Some("all") => true,
+        Some(_) => {
+            return Err(Error::ValidationFailed(
+                json![{"tagMatch": "must be either any or all"}],
+            ))
+        }
+    };
+    let filter = ArticleFilter {
+        tags: filters.tags,
+        all_tags,
+        excluded_tags: filters.excluded_tags,
+        authors: filters.authors,
+        favorited: filters.favorited,
+        since: filters.since.map(|t| parse_bound("since", &t)).transpose()?,
+        until: filters.until.map(|t| parse_bound("until", &t)).transpose()?,
+    };
     db::articles::articles(
         &conn,
-        tag,
-        author,
+        filter,
         offset,
         limit,
-        favorited,
         auth.map(|a| a.id),
+        sort,
+        Cursor::parse(cursor)?,
     )
 }
 
@@ -37,7 +117,10 @@ pub fn new_article(
     conn: DbConnection,
     auth: AuthData,
     data: Json<ArticleWrapper<NewArticleData>>,
+    client_ip: ClientIp,
+    config: State<Config>,
 ) -> DbResult<Article> {
+    auth.require_scope("articles:write")?;
     let article = &data.article;
     let mut errors = json![{}];
     let mut error = false;
@@ -53,27 +136,77 @@ pub fn new_article(
         errors["title"] = json!["is empty"].0;
         error = true;
     }
+    if !valid_status(&article.status) {
+        errors["status"] = json!["must be one of draft, published or unlisted"].0;
+        error = true;
+    }
 
     if error {
         Err(Error::ValidationFailed(errors))
     } else {
-        db::articles::create(&conn, &article, auth.id)
+        if config.require_verified_email {
+            db::users::ensure_verified(&conn, auth.id)?;
+        }
+        db::articles::create(&conn, article, auth.id, &config.slugs, client_ip.0)
     }
 }
 
-#[get("/articles/feed?<limit>&<offset>")]
+#[get("/articles/feed?<limit>&<offset>&<cursor>")]
 pub fn feed(
     conn: DbConnection,
     auth: AuthData,
     limit: Option<i32>,
     offset: Option<i32>,
+    cursor: Option<String>,
 ) -> DbResult<ArticleList> {
-    db::articles::user_feed(&conn, auth.id, limit, offset)
+    db::articles::user_feed(&conn, auth.id, limit, offset, Cursor::parse(cursor)?)
+}
+
+/// Full-text search over titles, descriptions, bodies and tags. `q` accepts quoted
+/// phrases, `or` and `-` exclusions.
+#[get("/articles/search?<q>&<limit>&<offset>")]
+pub fn search(
+    conn: DbConnection,
+    auth: Option<AuthData>,
+    q: Option<String>,
+    limit: Option<i32>,
+    offset: Option<i32>,
+) -> DbResult<ArticleMatchList> {
+    match q.filter(|q| !q.trim().is_empty()) {
+        Some(q) => db::articles::search(&conn, q, auth.map(|a| a.id), limit, offset),
+        None => Err(Error::ValidationFailed(json![{"q": "is empty"}])),
+    }
+}
+
+/// An article, or a permanent redirect when it was requested under a former slug.
+pub enum ArticleLookup {
+    Found(Article),
+    Moved(Redirect),
+}
+
+impl<'r> Responder<'r> for ArticleLookup {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        match self {
+            ArticleLookup::Found(article) => article.respond_to(req),
+            ArticleLookup::Moved(redirect) => redirect.respond_to(req),
+        }
+    }
 }
 
 #[get("/articles/<slug>")]
-pub fn article(conn: DbConnection, auth: Option<AuthData>, slug: String) -> DbResult<Article> {
-    db::articles::article(&conn, auth.map(|a| a.id), slug)
+pub fn article(
+    conn: DbConnection,
+    auth: Option<AuthData>,
+    slug: String,
+) -> DbResult<ArticleLookup> {
+    let canonical = db::slug_history::canonical(&conn, &slug)?;
+    // Only redirect those who can see the article, which keeps the new slugs of drafts private
+    let found = db::articles::article(&conn, auth.map(|a| a.id), canonical)?;
+    if found.slug == slug {
+        Ok(ArticleLookup::Found(found))
+    } else {
+        Ok(ArticleLookup::Moved(Redirect::moved(uri!("/api", article: found.slug))))
+    }
 }
 
 #[put("/articles/<slug>", data = "<data>", format = "json")]
@@ -82,7 +215,12 @@ pub fn update_article(
     auth: AuthData,
     slug: String,
     data: Json<ArticleWrapper<UpdateArticleData>>,
+    if_match: IfMatch,
+    client_ip: ClientIp,
+    config: State<Config>,
 ) -> DbResult<Article> {
+    auth.require_scope("articles:write")?;
+    require_precondition(&if_match, &config)?;
     let article = &data.article;
     let mut errors = json![{}];
     let mut error = false;
@@ -113,30 +251,170 @@ pub fn update_article(
         }
         None => (),
     };
+    if !valid_status(&article.status) {
+        errors["status"] = json!["must be one of draft, published or unlisted"].0;
+        error = true;
+    }
 
     if error {
         Err(Error::ValidationFailed(errors))
     } else {
-        db::articles::update(&conn, auth.id, slug, &article)
+        let moderate = auth.role(&conn, &config)? >= Role::Moderator;
+        let slug = db::slug_history::canonical(&conn, &slug)?;
+        let edit = db::articles::ArticleEdit {
+            editor: auth.id,
+            slug,
+            data: article,
+            moderate,
+            if_match: &if_match.0,
+        };
+        db::articles::update(&conn, edit, &config.slugs, client_ip.0)
     }
 }
 
 #[delete("/articles/<slug>")]
-pub fn delete_article(conn: DbConnection, auth: AuthData, slug: String) -> DbResult<Article> {
-    db::articles::delete(&conn, auth.id, slug)
+pub fn delete_article(
+    conn: DbConnection,
+    auth: AuthData,
+    slug: String,
+    if_match: IfMatch,
+    client_ip: ClientIp,
+    config: State<Config>,
+) -> DbResult<Article> {
+    auth.require_scope("articles:write")?;
+    require_precondition(&if_match, &config)?;
+    let moderate = auth.role(&conn, &config)? >= Role::Moderator;
+    let slug = db::slug_history::canonical(&conn, &slug)?;
+    db::articles::delete(&conn, auth.id, slug, moderate, &if_match.0, client_ip.0)
+}
+
+#[get("/user/drafts?<limit>&<offset>")]
+pub fn drafts(
+    conn: DbConnection,
+    auth: AuthData,
+    limit: Option<i32>,
+    offset: Option<i32>,
+) -> DbResult<ArticleList> {
+    db::articles::drafts(&conn, auth.id, limit, offset)
+}
+
+#[post("/articles/<slug>/publish")]
+pub fn publish(
+    conn: DbConnection,
+    auth: AuthData,
+    slug: String,
+    client_ip: ClientIp,
+) -> DbResult<Article> {
+    auth.require_scope("articles:write")?;
+    let slug = db::slug_history::canonical(&conn, &slug)?;
+    db::articles::publish(&conn, auth.id, slug, client_ip.0)
 }
 
 #[post("/articles/<slug>/favorite")]
 pub fn favorite(conn: DbConnection, auth: AuthData, slug: String) -> DbResult<Article> {
+    auth.require_scope("favorites:write")?;
+    let slug = db::slug_history::canonical(&conn, &slug)?;
     db::articles::favorite(&conn, auth.id, &slug)
 }
 
 #[delete("/articles/<slug>/favorite")]
 pub fn unfavorite(conn: DbConnection, auth: AuthData, slug: String) -> DbResult<Article> {
+    auth.require_scope("favorites:write")?;
+    let slug = db::slug_history::canonical(&conn, &slug)?;
     db::articles::unfavorite(&conn, auth.id, &slug)
 }
 
-#[get("/tags")]
-pub fn tags(conn: DbConnection) -> DbResult<TagList> {
-    db::articles::tags(&conn)
+#[get("/articles/<slug>/revisions?<limit>&<offset>")]
+pub fn revisions(
+    conn: DbConnection,
+    auth: Option<AuthData>,
+    slug: String,
+    limit: Option<i32>,
+    offset: Option<i32>,
+) -> DbResult<RevisionList> {
+    let slug = db::slug_history::canonical(&conn, &slug)?;
+    db::revisions::list(&conn, auth.map(|a| a.id), slug, limit, offset)
+}
+
+#[get("/articles/<slug>/revisions/<revision>")]
+pub fn revision(
+    conn: DbConnection,
+    auth: Option<AuthData>,
+    slug: String,
+    revision: i32,
+) -> DbResult<Revision> {
+    let slug = db::slug_history::canonical(&conn, &slug)?;
+    db::revisions::find(&conn, auth.map(|a| a.id), slug, revision)
+}
+
+#[get("/articles/<slug>/revisions/<from>/diff/<to>")]
+pub fn revision_diff(
+    conn: DbConnection,
+    auth: Option<AuthData>,
+    slug: String,
+    from: i32,
+    to: i32,
+) -> DbResult<RevisionDiff> {
+    let slug = db::slug_history::canonical(&conn, &slug)?;
+    db::revisions::diff(&conn, auth.map(|a| a.id), slug, from, to)
+}
+
+#[post("/articles/<slug>/revisions/<revision>/restore")]
+pub fn restore_revision(
+    conn: DbConnection,
+    auth: AuthData,
+    slug: String,
+    revision: i32,
+    if_match: IfMatch,
+    client_ip: ClientIp,
+    config: State<Config>,
+) -> DbResult<Article> {
+    auth.require_scope("articles:write")?;
+    require_precondition(&if_match, &config)?;
+    let slug = db::slug_history::canonical(&conn, &slug)?;
+    db::revisions::restore(
+        &conn,
+        auth.id,
+        slug,
+        revision,
+        &if_match.0,
+        &config.slugs,
+        client_ip.0,
+    )
+}
+
+/// Most used tags, or suggestions for the partial tag `q`.
+#[get("/tags?<q>&<limit>")]
+pub fn tags(conn: DbConnection, q: Option<String>, limit: Option<i32>) -> DbResult<TagList> {
+    match q {
+        Some(q) if q.trim().is_empty() => Err(Error::ValidationFailed(json![{"q": "is empty"}])),
+        q => db::articles::tags(&conn, q.map(|q| q.trim().to_lowercase()), limit),
+    }
+}
+
+// Dates stand for midnight UTC
+fn parse_bound(field: &str, value: &str) -> DbResult<NaiveDateTime> {
+    decode_rfc3339(value)
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(|| {
+            Error::ValidationFailed(json!({ field: "must be a date or an RFC 3339 timestamp" }))
+        })
+}
+
+fn valid_status(status: &Option<String>) -> bool {
+    status
+        .as_ref()
+        .map_or(true, |s| ArticleStatus::parse(s).is_some())
+}
+
+fn require_precondition(if_match: &IfMatch, config: &Config) -> DbResult<()> {
+    if config.require_if_match && if_match.0.is_none() {
+        Err(Error::PreconditionRequired)
+    } else {
+        Ok(())
+    }
 }
@@ -1,8 +1,13 @@
 use crate::authentication::AuthData;
+use crate::client_ip::ClientIp;
+use crate::config::Config;
+use crate::cursor::Cursor;
 use crate::db;
 use crate::db::{DbConnection, DbResult};
 use crate::errors::Error;
 use crate::models::comment::{Comment, CommentList, NewCommentData};
+use crate::models::user::Role;
+use rocket::State;
 use rocket_contrib::json::Json;
 
 #[derive(Deserialize)]
@@ -10,9 +15,18 @@ pub struct CommentWrapper {
     comment: NewCommentData,
 }
 
-#[get("/articles/<slug>/comments")]
-pub fn comments(conn: DbConnection, auth: Option<AuthData>, slug: String) -> DbResult<CommentList> {
-    db::comments::for_article(&conn, auth.map(|a| a.id), slug)
+/// All comments, or pages of them when `limit` or `cursor` is given.
+#[get("/articles/<slug>/comments?<limit>&<cursor>")]
+pub fn comments(
+    conn: DbConnection,
+    auth: Option<AuthData>,
+    slug: String,
+    limit: Option<i32>,
+    cursor: Option<String>,
+) -> DbResult<CommentList> {
+    let cursor = Cursor::parse(cursor)?;
+    let slug = db::slug_history::canonical(&conn, &slug)?;
+    db::comments::for_article(&conn, auth.map(|a| a.id), slug, limit, cursor)
 }
 
 #[post("/articles/<slug>/comments", data = "<comment>", format = "json")]
@@ -21,11 +35,18 @@ pub fn new_comment(
     auth: AuthData,
     slug: String,
     comment: Json<CommentWrapper>,
+    client_ip: ClientIp,
+    config: State<Config>,
 ) -> DbResult<Comment> {
+    auth.require_scope("comments:write")?;
     if comment.comment.body.is_empty() {
         Err(Error::ValidationFailed(json![{"body": "is empty"}]))
     } else {
-        db::comments::create(&conn, auth.id, &slug, &comment.comment)
+        if config.require_verified_email {
+            db::users::ensure_verified(&conn, auth.id)?;
+        }
+        let slug = db::slug_history::canonical(&conn, &slug)?;
+        db::comments::create(&conn, auth.id, &slug, &comment.comment, client_ip.0)
     }
 }
 
@@ -35,6 +56,10 @@ pub fn delete_comment(
     auth: AuthData,
     slug: String,
     comment_id: i32,
+    client_ip: ClientIp,
+    config: State<Config>,
 ) -> DbResult<Comment> {
-    db::comments::delete(&conn, auth.id, &slug, comment_id)
+    auth.require_scope("comments:write")?;
+    let moderate = auth.role(&conn, &config)? >= Role::Moderator;
+    db::comments::delete(&conn, auth.id, &slug, comment_id, moderate, client_ip.0)
 }
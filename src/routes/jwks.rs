@@ -0,0 +1,9 @@
+use crate::config::Config;
+use rocket::State;
+use rocket_contrib::json::JsonValue;
+
+/// Public keys other services can use to verify our tokens. Empty when tokens are HMAC signed.
+#[get("/.well-known/jwks.json")]
+pub fn jwks(config: State<Config>) -> JsonValue {
+    json![{ "keys": config.jwt_keys.jwks() }]
+}
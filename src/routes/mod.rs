@@ -1,3 +1,7 @@
+pub mod admin;
+pub mod api_keys;
 pub mod articles;
 pub mod comments;
+pub mod jwks;
+pub mod two_factor;
 pub mod users;
\ No newline at end of file
@@ -0,0 +1,46 @@
+use crate::authentication::AuthData;
+use crate::db;
+use crate::db::{DbConnection, DbResult};
+use crate::models::two_factor::{RecoveryCodes, TwoFactorCode, TwoFactorSetup};
+use crate::totp;
+use rocket::response::status::NoContent;
+use rocket_contrib::json::Json;
+
+const ISSUER: &str = "Conduit";
+
+#[derive(Deserialize)]
+pub struct TwoFactorWrapper<T> {
+    #[serde(rename = "twoFactor")]
+    two_factor: T,
+}
+
+#[post("/user/2fa")]
+pub fn enroll(conn: DbConnection, auth: AuthData) -> DbResult<TwoFactorSetup> {
+    auth.require_session()?;
+    let user = db::users::find_by_id(&conn, auth.id)?;
+    db::two_factor::begin_enrollment(&conn, auth.id).map(|secret| TwoFactorSetup {
+        otpauth_uri: totp::provisioning_uri(&secret, &user.email, ISSUER),
+        secret,
+    })
+}
+
+#[post("/user/2fa/confirm", data = "<data>", format = "json")]
+pub fn confirm(
+    conn: DbConnection,
+    auth: AuthData,
+    data: Json<TwoFactorWrapper<TwoFactorCode>>,
+) -> DbResult<RecoveryCodes> {
+    auth.require_session()?;
+    db::two_factor::confirm_enrollment(&conn, auth.id, &data.two_factor.code)
+        .map(|recovery_codes| RecoveryCodes { recovery_codes })
+}
+
+#[delete("/user/2fa", data = "<data>", format = "json")]
+pub fn disable(
+    conn: DbConnection,
+    auth: AuthData,
+    data: Json<TwoFactorWrapper<TwoFactorCode>>,
+) -> DbResult<NoContent> {
+    auth.require_session()?;
+    db::two_factor::disable(&conn, auth.id, &data.two_factor.code).map(|_| NoContent)
+}
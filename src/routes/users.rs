@@ -1,11 +1,18 @@
-use crate::authentication::AuthData;
+use crate::authentication::{AuthData, SESSION_COOKIE};
+use crate::client_ip::ClientIp;
+use crate::conditional;
 use crate::config::Config;
 use crate::db;
 use crate::db::{DbConnection, DbResult};
 use crate::errors::Error;
+use crate::mailer;
+use crate::mailer::Mailer;
+use crate::models::two_factor::TwoFactorLoginData;
 use crate::models::user::*;
 use regex;
+use rocket::http::{Cookie, Cookies, SameSite};
 use rocket::response;
+use rocket::response::status::NoContent;
 use rocket::response::Responder;
 use rocket::Request;
 use rocket::State;
@@ -27,7 +34,24 @@ impl<'r> Responder<'r> for AuthenticatedUser {
 
 impl<'r> Responder<'r> for Profile {
     fn respond_to(self, req: &Request) -> response::Result<'r> {
-        json![{ "profile": self }].respond_to(req)
+        conditional::respond(req, json![{ "profile": self }], None, None)
+    }
+}
+
+impl<'r> Responder<'r> for ProfileList {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        conditional::respond(req, json![{ "profiles": self.0 }], None, None)
+    }
+}
+
+impl<'r> Responder<'r> for LoginResponse {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        match self {
+            LoginResponse::Authenticated(user) => user.respond_to(req),
+            LoginResponse::SecondFactorRequired(challenge) => {
+                json![{ "twoFactor": { "challenge": challenge } }].respond_to(req)
+            }
+        }
     }
 }
 
@@ -35,16 +59,119 @@ impl<'r> Responder<'r> for Profile {
 pub fn login(
     conn: DbConnection,
     user: Json<UserWrapper<LoginData>>,
+    client_ip: ClientIp,
+    mut cookies: Cookies,
+    config: State<Config>,
+) -> DbResult<LoginResponse> {
+    let response = db::users::authenticate(
+        &conn,
+        &user.user.email,
+        &user.user.password,
+        client_ip.0,
+        &config,
+    )?;
+    Ok(match response {
+        LoginResponse::Authenticated(user) => {
+            LoginResponse::Authenticated(start_session(&mut cookies, &config, user))
+        }
+        challenge => challenge,
+    })
+}
+
+#[post("/users/login/2fa", data = "<data>", format = "json")]
+pub fn login_second_factor(
+    conn: DbConnection,
+    data: Json<UserWrapper<TwoFactorLoginData>>,
+    mut cookies: Cookies,
+    config: State<Config>,
+) -> DbResult<AuthenticatedUser> {
+    db::users::complete_two_factor(&conn, &data.user.challenge, &data.user.code, &config)
+        .map(|user| start_session(&mut cookies, &config, user))
+}
+
+#[post("/users/token/refresh", data = "<data>", format = "json")]
+pub fn refresh_token(
+    conn: DbConnection,
+    data: Json<UserWrapper<RefreshData>>,
+    mut cookies: Cookies,
     config: State<Config>,
 ) -> DbResult<AuthenticatedUser> {
-    db::users::authenticate(&conn, &user.user.email, &user.user.password, &config.secret)
+    db::users::refresh(&conn, &data.user.refresh_token, &config)
+        .map(|user| start_session(&mut cookies, &config, user))
+}
+
+#[post("/users/logout", data = "<data>")]
+pub fn logout(
+    conn: DbConnection,
+    auth: AuthData,
+    data: Option<Json<UserWrapper<RefreshData>>>,
+    mut cookies: Cookies,
+) -> DbResult<NoContent> {
+    auth.require_session()?;
+    db::users::logout(
+        &conn,
+        &auth,
+        data.as_ref().map(|d| &d.user.refresh_token),
+    )?;
+    end_session(&mut cookies);
+    Ok(NoContent)
+}
+
+/// Ends every session of the user and revokes their API keys.
+#[post("/users/logout/all")]
+pub fn logout_everywhere(
+    conn: DbConnection,
+    auth: AuthData,
+    mut cookies: Cookies,
+) -> DbResult<NoContent> {
+    auth.require_session()?;
+    db::revocations::revoke_all(&conn, auth.id)?;
+    end_session(&mut cookies);
+    Ok(NoContent)
+}
+
+#[post("/users/password/reset", data = "<data>", format = "json")]
+pub fn request_password_reset(
+    conn: DbConnection,
+    data: Json<UserWrapper<PasswordResetRequest>>,
+    config: State<Config>,
+    transport: State<Box<dyn Mailer>>,
+) -> DbResult<NoContent> {
+    // Failures aren't reported, the response must not tell which emails have an account
+    if let Some((user, token)) = db::password_resets::request(&conn, &data.user.email, &config)? {
+        let mail = mailer::password_reset(&user.email, &token, &config.app_url);
+        if let Err(err) = transport.send(mail) {
+            warn!(
+                "password reset mail for user {} not sent: {:?}",
+                user.id, err
+            );
+        }
+    }
+    Ok(NoContent)
+}
+
+#[post("/users/password/reset/confirm", data = "<data>", format = "json")]
+pub fn confirm_password_reset(
+    conn: DbConnection,
+    data: Json<UserWrapper<PasswordResetData>>,
+    config: State<Config>,
+) -> DbResult<NoContent> {
+    let reset = &data.user;
+    if reset.password.is_empty() {
+        Err(Error::ValidationFailed(json![{"password": "is empty"}]))
+    } else {
+        db::password_resets::confirm(&conn, &reset.token, &reset.password, &config)
+            .map(|_| NoContent)
+    }
 }
 
 #[post("/users", data = "<data>", format = "json")]
 pub fn register(
     conn: DbConnection,
     data: Json<UserWrapper<NewUserData>>,
+    mut cookies: Cookies,
     config: State<Config>,
+    transport: State<Box<dyn Mailer>>,
 ) -> DbResult<AuthenticatedUser> {
     let user = &data.user;
     let mut errors = json![{}];
@@ -78,8 +205,20 @@ pub fn register(
             &user.username,
             &user.email,
             user.password.clone(),
-            &config.secret,
+            &config,
         )
+        .map(|created| {
+            // The account exists by then, and the mail can be sent again from it
+            if let Err(err) =
+                send_verification(&conn, &config, &**transport, created.id, &created.email)
+            {
+                warn!(
+                    "verification mail for user {} not sent: {:?}",
+                    created.id, err
+                );
+            }
+            start_session(&mut cookies, &config, created)
+        })
     }
 }
 
@@ -89,7 +228,8 @@ pub fn current_user(
     auth: AuthData,
     config: State<Config>,
 ) -> DbResult<AuthenticatedUser> {
-    db::users::find_by_id(&conn, auth.id).and_then(|u| u.to_authenticated(&config.secret))
+    let user = db::users::find_by_id(&conn, auth.id)?.to_authenticated(&config)?;
+    Ok(for_credentials(&auth, user))
 }
 
 #[put("/user", data = "<data>", format = "json")]
@@ -97,9 +237,16 @@ pub fn update_current_user(
     conn: DbConnection,
     auth: AuthData,
     data: Json<UserWrapper<UserUpdateData>>,
+    client_ip: ClientIp,
     config: State<Config>,
+    transport: State<Box<dyn Mailer>>,
 ) -> DbResult<AuthenticatedUser> {
+    auth.require_scope("user:write")?;
     let user = &data.user;
+    // Credentials can only be changed from a session, so a leaked key can't take the account over
+    if user.email.is_some() || user.password.is_some() {
+        auth.require_session()?;
+    }
     let mut error = false;
     let mut errors = json![{}];
     match &user.username {
@@ -137,7 +284,43 @@ pub fn update_current_user(
     if error {
         Err(Error::ValidationFailed(errors))
     } else {
-        db::users::update(&conn, auth.id, &user, &config.secret)
+        let updated = db::users::update(&conn, auth.id, user, &config, client_ip.0)?;
+        if user.email.is_some() && !updated.verified {
+            // The change is saved by then, and the mail can be sent again from the account
+            if let Err(err) =
+                send_verification(&conn, &config, &**transport, updated.id, &updated.email)
+            {
+                warn!(
+                    "verification mail for user {} not sent: {:?}",
+                    updated.id, err
+                );
+            }
+        }
+        Ok(for_credentials(&auth, updated))
+    }
+}
+
+#[post("/users/verify", data = "<data>", format = "json")]
+pub fn verify_email(
+    conn: DbConnection,
+    data: Json<UserWrapper<VerificationData>>,
+) -> DbResult<NoContent> {
+    db::email_verifications::confirm(&conn, &data.user.token).map(|_| NoContent)
+}
+
+#[post("/user/verification")]
+pub fn resend_verification(
+    conn: DbConnection,
+    auth: AuthData,
+    config: State<Config>,
+    transport: State<Box<dyn Mailer>>,
+) -> DbResult<NoContent> {
+    auth.require_session()?;
+    let user = db::users::find_by_id(&conn, auth.id)?;
+    if user.verified_at.is_some() {
+        Err(Error::ValidationFailed(json![{"email": "is already verified"}]))
+    } else {
+        send_verification(&conn, &config, &**transport, user.id, &user.email).map(|_| NoContent)
     }
 }
 
@@ -146,16 +329,76 @@ pub fn profile(conn: DbConnection, username: String, auth: Option<AuthData>) ->
     db::users::profile(&conn, &username, &auth)
 }
 
+/// Suggestions for the partial or misspelled username `q`.
+#[get("/profiles?<q>&<limit>")]
+pub fn profiles(
+    conn: DbConnection,
+    auth: Option<AuthData>,
+    q: Option<String>,
+    limit: Option<i32>,
+) -> DbResult<ProfileList> {
+    match q.filter(|q| !q.trim().is_empty()) {
+        Some(q) => db::users::search(&conn, q.trim().to_owned(), limit, auth.map(|a| a.id)),
+        None => Err(Error::ValidationFailed(json![{"q": "is empty"}])),
+    }
+}
+
 #[post("/profiles/<username>/follow")]
 pub fn follow(conn: DbConnection, username: String, auth: AuthData) -> DbResult<Profile> {
+    auth.require_scope("follows:write")?;
     db::users::follow(&conn, &username, auth.id)
 }
 
 #[delete("/profiles/<username>/follow")]
 pub fn unfollow(conn: DbConnection, username: String, auth: AuthData) -> DbResult<Profile> {
+    auth.require_scope("follows:write")?;
     db::users::unfollow(&conn, &username, auth.id)
 }
 
+// Hands the access token to browser clients as an http-only cookie, when enabled.
+fn start_session(
+    cookies: &mut Cookies,
+    config: &Config,
+    user: AuthenticatedUser,
+) -> AuthenticatedUser {
+    if let (true, Some(token)) = (config.session_cookie, &user.token) {
+        cookies.add(
+            Cookie::build(SESSION_COOKIE, token.clone())
+                .path("/")
+                .http_only(true)
+                .secure(!cfg!(debug_assertions))
+                .same_site(SameSite::Strict)
+                .finish(),
+        );
+    }
+    user
+}
+
+// Requests made with an API key never get a session token back
+fn for_credentials(auth: &AuthData, user: AuthenticatedUser) -> AuthenticatedUser {
+    match auth.api_key {
+        Some(_) => user.without_token(),
+        None => user,
+    }
+}
+
+fn end_session(cookies: &mut Cookies) {
+    cookies.remove(Cookie::build(SESSION_COOKIE, "").path("/").finish());
+}
+
+fn send_verification(
+    conn: &DbConnection,
+    config: &Config,
+    transport: &dyn Mailer,
+    user_id: i32,
+    email: &String,
+) -> DbResult<()> {
+    let token = db::email_verifications::create(conn, user_id, email, config)?;
+    transport
+        .send(mailer::email_verification(email, &token, &config.app_url))
+        .map_err(|err| Error::InternalServerError("mail".to_owned(), err))
+}
+
 fn email_regex() -> DbResult<regex::Regex> {
     regex::Regex::new(r"^([a-zA-Z0-9_\-\.]+)@([a-zA-Z0-9_\-\.]+)\.([a-zA-Z]{2,5})$")
         .map_err(|err| Error::InternalServerError("email regex".to_owned(), err.to_string()))
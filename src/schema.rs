@@ -1,3 +1,39 @@
+table! {
+    api_keys (id) {
+        id -> Int4,
+        user_id -> Int4,
+        name -> Text,
+        prefix -> Text,
+        key_hash -> Text,
+        scopes -> Nullable<Array<Text>>,
+        created_at -> Timestamptz,
+        last_used_at -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    article_revisions (id) {
+        id -> Int4,
+        article_id -> Int4,
+        revision -> Int4,
+        title -> Text,
+        description -> Text,
+        body -> Text,
+        tag_list -> Array<Text>,
+        editor_id -> Nullable<Int4>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    article_slug_history (slug) {
+        slug -> Text,
+        article_id -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
 table! {
     article_tag_associations (article_id, tag_id) {
         article_id -> Int4,
@@ -16,6 +52,21 @@ table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         favorites_count -> Int4,
+        status -> Text,
+        published_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    audit_events (id) {
+        id -> Int4,
+        actor_id -> Nullable<Int4>,
+        action -> Text,
+        target_type -> Text,
+        target_id -> Text,
+        ip -> Nullable<Text>,
+        diff -> Jsonb,
+        created_at -> Timestamptz,
     }
 }
 
@@ -30,6 +81,18 @@ table! {
     }
 }
 
+table! {
+    email_verification_tokens (id) {
+        id -> Int4,
+        user_id -> Int4,
+        email -> Text,
+        token_hash -> Text,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+    }
+}
+
 table! {
     favorites (user_id, article_id) {
         user_id -> Int4,
@@ -44,6 +107,74 @@ table! {
     }
 }
 
+table! {
+    login_challenges (id) {
+        id -> Int4,
+        user_id -> Int4,
+        token_hash -> Text,
+        attempts -> Int4,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+    }
+}
+
+table! {
+    login_throttles (key) {
+        key -> Text,
+        failures -> Int4,
+        last_failure_at -> Timestamptz,
+        locked_until -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    moderation_actions (id) {
+        id -> Int4,
+        moderator_id -> Nullable<Int4>,
+        author_id -> Nullable<Int4>,
+        action -> Text,
+        target -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    password_reset_tokens (id) {
+        id -> Int4,
+        user_id -> Int4,
+        token_hash -> Text,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    recovery_codes (id) {
+        id -> Int4,
+        user_id -> Int4,
+        code_hash -> Text,
+        used_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    refresh_tokens (id) {
+        id -> Int4,
+        user_id -> Int4,
+        token_hash -> Text,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+    }
+}
+
+table! {
+    revoked_tokens (jti) {
+        jti -> Text,
+        expires_at -> Timestamptz,
+    }
+}
+
 table! {
     tags (id) {
         id -> Int4,
@@ -51,6 +182,16 @@ table! {
     }
 }
 
+table! {
+    totp_credentials (user_id) {
+        user_id -> Int4,
+        secret -> Text,
+        created_at -> Timestamptz,
+        confirmed_at -> Nullable<Timestamptz>,
+        last_used_step -> Nullable<Int8>,
+    }
+}
+
 table! {
     users (id) {
         id -> Int4,
@@ -59,23 +200,50 @@ table! {
         bio -> Nullable<Text>,
         image -> Nullable<Text>,
         hash -> Text,
+        token_version -> Int4,
+        verified_at -> Nullable<Timestamptz>,
+        role -> Text,
+        suspended_at -> Nullable<Timestamptz>,
     }
 }
 
+joinable!(api_keys -> users (user_id));
+joinable!(article_revisions -> articles (article_id));
+joinable!(article_revisions -> users (editor_id));
+joinable!(article_slug_history -> articles (article_id));
 joinable!(article_tag_associations -> articles (article_id));
 joinable!(article_tag_associations -> tags (tag_id));
 joinable!(articles -> users (author));
 joinable!(comments -> articles (article_id));
 joinable!(comments -> users (user_id));
+joinable!(email_verification_tokens -> users (user_id));
 joinable!(favorites -> articles (article_id));
 joinable!(favorites -> users (user_id));
+joinable!(login_challenges -> users (user_id));
+joinable!(password_reset_tokens -> users (user_id));
+joinable!(recovery_codes -> users (user_id));
+joinable!(refresh_tokens -> users (user_id));
+joinable!(totp_credentials -> users (user_id));
 
 allow_tables_to_appear_in_same_query!(
+    api_keys,
+    article_revisions,
+    article_slug_history,
     article_tag_associations,
     articles,
+    audit_events,
     comments,
+    email_verification_tokens,
     favorites,
     followings,
+    login_challenges,
+    login_throttles,
+    moderation_actions,
+    password_reset_tokens,
+    recovery_codes,
+    refresh_tokens,
+    revoked_tokens,
     tags,
+    totp_credentials,
     users,
 );
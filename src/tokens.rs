@@ -0,0 +1,15 @@
+use rand::distributions::Alphanumeric;
+use rand::*;
+use sha2::{Digest, Sha256};
+
+const TOKEN_LEN: usize = 48;
+
+/// Opaque random token handed out to clients. Only its hash is ever persisted.
+pub fn generate() -> String {
+    let mut rng = thread_rng();
+    (0..TOKEN_LEN).map(|_| rng.sample(Alphanumeric)).collect()
+}
+
+pub fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
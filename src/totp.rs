@@ -0,0 +1,151 @@
+use base32::Alphabet;
+use chrono::Utc;
+use hmac::{Hmac, Mac, NewMac};
+use rand::distributions::Alphanumeric;
+use rand::*;
+use rocket::http::uri::Uri;
+use sha1::Sha1;
+
+const STEP: i64 = 30;
+const DIGITS: u32 = 6;
+const SECRET_LEN: usize = 20;
+/// Number of steps accepted before and after the current one, to absorb clock drift.
+const SKEW: i64 = 1;
+const RECOVERY_CODE_LEN: usize = 10;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+const ALPHABET: Alphabet = Alphabet::RFC4648 { padding: false };
+
+pub fn generate_secret() -> String {
+    let bytes: [u8; SECRET_LEN] = thread_rng().gen();
+    base32::encode(ALPHABET, &bytes)
+}
+
+/// URI understood by authenticator apps, usually rendered as a QR code.
+pub fn provisioning_uri(secret: &str, account: &str, issuer: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+        issuer = Uri::percent_encode(issuer),
+        account = Uri::percent_encode(account),
+        secret = secret,
+        digits = DIGITS,
+        period = STEP,
+    )
+}
+
+/// Time step of `code` if it is valid and later than `last_step`, the step of the
+/// last code accepted for the secret, so that codes can't be replayed.
+pub fn verify(secret: &str, code: &str, last_step: Option<i64>) -> Option<i64> {
+    verify_at(secret, code, last_step, Utc::now().timestamp())
+}
+
+// `verify` at the given Unix time
+fn verify_at(secret: &str, code: &str, last_step: Option<i64>, time: i64) -> Option<i64> {
+    let key = base32::decode(ALPHABET, secret)?;
+    let code = code.trim();
+    let current = time / STEP;
+    (current - SKEW..=current + SKEW)
+        .filter(|step| last_step.map_or(true, |last| *step > last))
+        .find(|step| code_at(&key, *step as u64, DIGITS) == code)
+}
+
+pub fn generate_recovery_code() -> String {
+    let mut rng = thread_rng();
+    let code: String = (0..RECOVERY_CODE_LEN)
+        .map(|_| rng.sample(Alphanumeric))
+        .collect::<String>()
+        .to_lowercase();
+    format!("{}-{}", &code[..RECOVERY_CODE_LEN / 2], &code[RECOVERY_CODE_LEN / 2..])
+}
+
+/// Recovery codes are compared case-insensitively and without their separator.
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.trim()
+        .chars()
+        .filter(|c| *c != '-')
+        .collect::<String>()
+        .to_lowercase()
+}
+
+// RFC 4226 HOTP value for the given counter
+fn code_at(key: &[u8], counter: u64, digits: u32) -> String {
+    let mut mac = match Hmac::<Sha1>::new_varkey(key) {
+        Ok(mac) => mac,
+        Err(_) => return String::new(),
+    };
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = ((digest[offset] as u32 & 0x7f) << 24)
+        | ((digest[offset + 1] as u32) << 16)
+        | ((digest[offset + 2] as u32) << 8)
+        | (digest[offset + 3] as u32);
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(digits),
+        width = digits as usize
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The RFC 6238 SHA1 seed, "12345678901234567890"
+    const SEED: &[u8] = b"12345678901234567890";
+
+    fn secret() -> String {
+        base32::encode(ALPHABET, SEED)
+    }
+
+    #[test]
+    fn matches_rfc_6238_vectors() {
+        let vectors = [
+            (59, "94287082"),
+            (1111111109, "07081804"),
+            (1111111111, "14050471"),
+            (1234567890, "89005924"),
+            (2000000000, "69279037"),
+            (20000000000, "65353130"),
+        ];
+        for (time, expected) in vectors.iter() {
+            assert_eq!(code_at(SEED, (time / STEP) as u64, 8), *expected);
+        }
+    }
+
+    #[test]
+    fn matches_rfc_4226_vectors() {
+        let vectors = ["755224", "287082", "359152", "969429", "338314"];
+        for (counter, expected) in vectors.iter().enumerate() {
+            assert_eq!(code_at(SEED, counter as u64, DIGITS), *expected);
+        }
+    }
+
+    #[test]
+    fn accepts_adjacent_steps() {
+        let time = 1111111111;
+        let step = time / STEP;
+        for accepted in [step - 1, step, step + 1].iter() {
+            let code = code_at(SEED, *accepted as u64, DIGITS);
+            assert_eq!(verify_at(&secret(), &code, None, time), Some(*accepted));
+        }
+        for rejected in [step - 2, step + 2].iter() {
+            let code = code_at(SEED, *rejected as u64, DIGITS);
+            assert_eq!(verify_at(&secret(), &code, None, time), None);
+        }
+    }
+
+    #[test]
+    fn rejects_replayed_codes() {
+        let time = 1111111111;
+        let step = time / STEP;
+        let code = code_at(SEED, step as u64, DIGITS);
+        assert_eq!(verify_at(&secret(), &code, Some(step - 1), time), Some(step));
+        assert_eq!(verify_at(&secret(), &code, Some(step), time), None);
+    }
+
+    #[test]
+    fn normalizes_recovery_codes() {
+        assert_eq!(normalize_recovery_code(" AbCdE-fGhIj "), "abcdefghij");
+    }
+}